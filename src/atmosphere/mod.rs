@@ -0,0 +1,84 @@
+use vec3::Vec3;
+use ray::Ray;
+use common::{rand, henyey_greenstein, sample_henyey_greenstein};
+
+/// A scene wide participating medium filling the space between surfaces,
+/// whose density decays exponentially with height, such as fog or haze.
+pub struct Atmosphere {
+    density: f64,
+    base_height: f64,
+    scale_height: f64,
+    albedo: Vec3,
+    g: f64,
+}
+
+impl Atmosphere {
+    /// Creates a medium with extinction coefficient `density` at
+    /// `base_height`, falling by a factor of `e` every `scale_height`. The
+    /// medium scatters a fraction `albedo` of light with a Henyey-Greenstein
+    /// phase function of anisotropy `g`.
+    pub fn new(density: f64, base_height: f64, scale_height: f64, albedo: Vec3, g: f64) -> Atmosphere {
+        Atmosphere {
+            density,
+            base_height,
+            scale_height,
+            albedo,
+            g,
+        }
+    }
+
+    pub fn albedo(&self) -> Vec3 {
+        self.albedo
+    }
+
+    pub fn phase(&self, cosine: f64) -> f64 {
+        henyey_greenstein(cosine, self.g)
+    }
+
+    pub fn sample_direction(&self, direction: &Vec3) -> Vec3 {
+        sample_henyey_greenstein(direction, self.g)
+    }
+
+    /// The extinction at the origin of `ray` and its rate of change per unit
+    /// distance along the ray.
+    fn coefficients(&self, ray: &Ray) -> (f64, f64) {
+        let a = self.density * (-(ray.origin.e[1] - self.base_height) / self.scale_height).exp();
+        let k = ray.direction.unit_vector().e[1] / self.scale_height;
+        (a, k)
+    }
+
+    fn optical_depth(&self, ray: &Ray, t: f64) -> f64 {
+        let (a, k) = self.coefficients(ray);
+        let distance = t * ray.direction.length();
+        if k.abs() < 1e-9 {
+            a * distance
+        } else {
+            a * (1. - (-k * distance).exp()) / k
+        }
+    }
+
+    pub fn transmittance(&self, ray: &Ray, t_max: f64) -> f64 {
+        (-self.optical_depth(ray, t_max)).exp()
+    }
+
+    /// Samples the ray parameter where the ray collides with the medium
+    /// before `t_max`, in proportion to the transmittance.
+    pub fn sample_distance(&self, ray: &Ray, t_max: f64) -> Option<f64> {
+        let (a, k) = self.coefficients(ray);
+        if a <= 0. {
+            return None;
+        }
+        let depth = -(1. - rand()).ln();
+        let distance = if k.abs() < 1e-9 {
+            depth / a
+        } else {
+            let x = 1. - depth * k / a;
+            if x <= 0. {
+                return None;
+            }
+            -x.ln() / k
+        };
+        let t = distance / ray.direction.length();
+        if t < t_max { Some(t) } else { None }
+    }
+}
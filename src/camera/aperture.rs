@@ -0,0 +1,143 @@
+use std::f64::consts::PI;
+
+use vec3::Vec3;
+use common::{random_in_unit_disc, rand, luminance};
+use common::distribution::Distribution2D;
+use texture::image::Image;
+
+/// The shape of the lens opening, which is the shape out of focus
+/// highlights take.
+pub enum Aperture {
+    Circle,
+    /// A regular polygon formed by straight blades.
+    Polygon(Polygon),
+    /// An arbitrary shape given by an image.
+    Mask(ApertureMask),
+}
+
+impl Aperture {
+    pub fn polygon(blades: u32, rotation: f64) -> Aperture {
+        Aperture::Polygon(Polygon::new(blades, rotation))
+    }
+
+    /// An aperture shaped like `image`, or `None` if it has no pixels or
+    /// they are all black.
+    pub fn mask(image: &Image) -> Option<Aperture> {
+        ApertureMask::new(image).map(Aperture::Mask)
+    }
+
+    /// A point on the aperture in the `xy` plane, in units of the lens radius.
+    pub fn sample(&self) -> Vec3 {
+        match *self {
+            Aperture::Circle => random_in_unit_disc(),
+            Aperture::Polygon(ref polygon) => polygon.sample(),
+            Aperture::Mask(ref mask) => mask.sample(),
+        }
+    }
+}
+
+/// A regular polygon inscribed in the unit circle.
+pub struct Polygon {
+    blades: u32,
+    rotation: f64,
+}
+
+impl Polygon {
+    /// A polygon formed by `blades` straight blades, rotated by `rotation`
+    /// degrees from a vertex pointing up. Fewer than 3 blades are taken as 3.
+    pub fn new(blades: u32, rotation: f64) -> Polygon {
+        Polygon {
+            blades: blades.max(3),
+            rotation,
+        }
+    }
+
+    fn sample(&self) -> Vec3 {
+        // The polygon is a fan of equal triangles around the centre, so a
+        // uniformly chosen triangle is sampled uniformly.
+        let step = 2. * PI / self.blades as f64;
+        let i = ((rand() * self.blades as f64) as u32).min(self.blades - 1);
+        let angle = PI / 2. + self.rotation.to_radians() + step * i as f64;
+        let a = Vec3::new(angle.cos(), angle.sin(), 0.);
+        let b = Vec3::new((angle + step).cos(), (angle + step).sin(), 0.);
+        let (mut s, mut t) = (rand(), rand());
+        if s + t > 1. {
+            s = 1. - s;
+            t = 1. - t;
+        }
+        a * s + b * t
+    }
+}
+
+/// An aperture shaped like an image, transmitting light in proportion to
+/// the luminance of its pixels. The longer side of the image spans the
+/// diameter of the lens, so a wide oval gives anamorphic bokeh.
+pub struct ApertureMask {
+    distribution: Distribution2D,
+    width: f64,
+    height: f64,
+}
+
+impl ApertureMask {
+    /// The mask of `image`, or `None` if it has no pixels or they are all
+    /// black, which would let no light through.
+    pub fn new(image: &Image) -> Option<ApertureMask> {
+        let (width, height) = (image.width(), image.height());
+        let mut weights = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                weights.push(luminance(&image.pixel(x, y)).max(0.));
+            }
+        }
+        if !weights.iter().any(|&w| w > 0.) {
+            return None;
+        }
+        let size = width.max(height) as f64;
+        Some(ApertureMask {
+            distribution: Distribution2D::new(&weights, width, height),
+            width: width as f64 / size,
+            height: height as f64 / size,
+        })
+    }
+
+    /// A point on the mask, chosen by importance so that rays are only
+    /// traced through the open parts of the aperture.
+    fn sample(&self) -> Vec3 {
+        let ((u, v), _) = self.distribution.sample(rand(), rand());
+        Vec3::new((2. * u - 1.) * self.width, (1. - 2. * v) * self.height, 0.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn polygon_has_at_least_three_blades() {
+        for blades in 0..3 {
+            let aperture = Aperture::polygon(blades, 0.);
+            for _ in 0..100 {
+                assert!(aperture.sample().length() <= 1.);
+            }
+        }
+    }
+
+    #[test]
+    fn mask_samples_open_pixels() {
+        let black = Vec3::new(0., 0., 0.);
+        let white = Vec3::new(1., 1., 1.);
+        let image = Image::new(2, 1, vec![black, white]);
+        let aperture = Aperture::mask(&image).unwrap();
+        for _ in 0..100 {
+            let p = aperture.sample();
+            assert!(p.e[0] >= 0. && p.e[0] <= 1. && p.e[1].abs() <= 0.5);
+        }
+    }
+
+    #[test]
+    fn rejects_closed_masks() {
+        assert!(Aperture::mask(&Image::new(0, 0, Vec::new())).is_none());
+        assert!(Aperture::mask(&Image::new(0, 4, Vec::new())).is_none());
+        assert!(Aperture::mask(&Image::new(2, 2, vec![Vec3::new(0., 0., 0.); 4])).is_none());
+    }
+}
@@ -0,0 +1,83 @@
+use vec3::Vec3;
+use ray::Ray;
+use camera::{Projection, basis};
+
+/// The six 90 degree views of a cube map, such as a reflection probe, laid
+/// out in an image three faces wide and two high. The top row holds the
+/// right, left and up faces and the bottom row the down, front and back
+/// faces.
+pub struct CubeMap {
+    origin: Vec3,
+    /// The forward, right and up axes of each face.
+    faces: [(Vec3, Vec3, Vec3); 6],
+}
+
+impl CubeMap {
+    pub fn new(lookfrom: Vec3, lookat: Vec3, vup: Vec3) -> CubeMap {
+        let (u, v, w) = basis(lookfrom, lookat, vup);
+        CubeMap {
+            origin: lookfrom,
+            faces: [
+                (u, w, v),
+                (-u, -w, v),
+                (v, u, w),
+                (-v, u, -w),
+                (-w, u, v),
+                (w, -u, v),
+            ],
+        }
+    }
+}
+
+impl Projection for CubeMap {
+    fn get_ray(&self, s: f64, t: f64) -> Ray {
+        let column = ((s * 3.) as usize).min(2);
+        let row = if t >= 0.5 { 0 } else { 1 };
+        let a = (s * 3. - column as f64) * 2. - 1.;
+        let b = ((t * 2.) - (1 - row) as f64) * 2. - 1.;
+        let (forward, right, up) = self.faces[row * 3 + column];
+        Ray::new(self.origin, forward + right * a + up * b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn direction(camera: &CubeMap, s: f64, t: f64) -> Vec3 {
+        camera.get_ray(s, t).direction.unit_vector()
+    }
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-6, "{:?} != {:?}", a.e, b.e);
+    }
+
+    #[test]
+    fn faces_look_along_the_axes() {
+        let camera = CubeMap::new(Vec3::new(0., 0., 0.), Vec3::new(0., 0., -1.), Vec3::new(0., 1., 0.));
+        let centres = [
+            (1. / 6., 0.75, Vec3::new(1., 0., 0.)),
+            (0.5, 0.75, Vec3::new(-1., 0., 0.)),
+            (5. / 6., 0.75, Vec3::new(0., 1., 0.)),
+            (1. / 6., 0.25, Vec3::new(0., -1., 0.)),
+            (0.5, 0.25, Vec3::new(0., 0., -1.)),
+            (5. / 6., 0.25, Vec3::new(0., 0., 1.)),
+        ];
+        for &(s, t, axis) in centres.iter() {
+            assert_close(direction(&camera, s, t), axis);
+        }
+    }
+
+    #[test]
+    fn neighbouring_faces_share_edges() {
+        let camera = CubeMap::new(Vec3::new(0., 0., 0.), Vec3::new(0., 0., -1.), Vec3::new(0., 1., 0.));
+        let edge = Vec3::new(1., 0., 1.).unit_vector();
+        // The right edge of the right face and the left edge of the back face.
+        assert_close(direction(&camera, 1. / 3. - 1e-9, 0.75), edge);
+        assert_close(direction(&camera, 2. / 3., 0.25), edge);
+        // The top edge of the front face and the bottom edge of the up face.
+        let edge = Vec3::new(0., 1., -1.).unit_vector();
+        assert_close(direction(&camera, 0.5, 0.5 - 1e-9), edge);
+        assert_close(direction(&camera, 5. / 6., 0.5), edge);
+    }
+}
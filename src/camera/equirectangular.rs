@@ -0,0 +1,42 @@
+use std::f64::consts::PI;
+
+use vec3::Vec3;
+use ray::Ray;
+use camera::{Projection, basis};
+
+/// A 360 degree panorama mapping longitude to the horizontal and latitude to
+/// the vertical axis of an image twice as wide as it is high, with the view
+/// direction in the centre.
+pub struct Equirectangular {
+    origin: Vec3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+}
+
+impl Equirectangular {
+    pub fn new(lookfrom: Vec3, lookat: Vec3, vup: Vec3) -> Equirectangular {
+        let (u, v, w) = basis(lookfrom, lookat, vup);
+        Equirectangular {
+            origin: lookfrom,
+            u,
+            v,
+            w,
+        }
+    }
+
+    /// The direction seen at a point of the image.
+    pub fn direction(&self, s: f64, t: f64) -> Vec3 {
+        let longitude = (s - 0.5) * 2. * PI;
+        let latitude = (t - 0.5) * PI;
+        self.u * (latitude.cos() * longitude.sin())
+            + self.v * latitude.sin()
+            - self.w * (latitude.cos() * longitude.cos())
+    }
+}
+
+impl Projection for Equirectangular {
+    fn get_ray(&self, s: f64, t: f64) -> Ray {
+        Ray::new(self.origin, self.direction(s, t))
+    }
+}
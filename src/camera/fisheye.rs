@@ -0,0 +1,61 @@
+use std::f64::consts::PI;
+
+use vec3::Vec3;
+use ray::Ray;
+use camera::{Projection, basis};
+
+/// How a fisheye lens maps the angle from its axis to the distance from the
+/// centre of the image.
+#[derive(Copy, Clone)]
+pub enum FisheyeMapping {
+    /// Distance proportional to the angle.
+    Equidistant,
+    /// Distance proportional to `sin(angle / 2)`, preserving solid angle.
+    Equisolid,
+}
+
+/// A fisheye lens whose field of view, in degrees, spans the circle
+/// inscribed in the image. The corners of a wider image continue the
+/// mapping, as with a full frame fisheye.
+pub struct Fisheye {
+    origin: Vec3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    half_fov: f64,
+    aspect: f64,
+    mapping: FisheyeMapping,
+}
+
+impl Fisheye {
+    pub fn new(lookfrom: Vec3, lookat: Vec3, vup: Vec3, fov: f64, aspect: f64, mapping: FisheyeMapping) -> Fisheye {
+        let (u, v, w) = basis(lookfrom, lookat, vup);
+        Fisheye {
+            origin: lookfrom,
+            u,
+            v,
+            w,
+            half_fov: fov.to_radians() / 2.,
+            aspect,
+            mapping,
+        }
+    }
+}
+
+impl Projection for Fisheye {
+    fn get_ray(&self, s: f64, t: f64) -> Ray {
+        let (mut x, mut y) = ((s * 2. - 1.) * self.aspect, t * 2. - 1.);
+        if self.aspect < 1. {
+            x /= self.aspect;
+            y /= self.aspect;
+        }
+        let r = (x * x + y * y).sqrt();
+        let theta = match self.mapping {
+            FisheyeMapping::Equidistant => r * self.half_fov,
+            FisheyeMapping::Equisolid => 2. * (r * (self.half_fov / 2.).sin()).min(1.).asin(),
+        }.min(PI);
+
+        let radial = if r > 0. { (self.u * x + self.v * y) / r } else { Vec3::new(0., 0., 0.) };
+        Ray::new(self.origin, radial * theta.sin() - self.w * theta.cos())
+    }
+}
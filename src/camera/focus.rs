@@ -0,0 +1,91 @@
+use vec3::Vec3;
+use ray::Ray;
+use hitable::{World, Hitable};
+use camera::Camera;
+
+/// What a camera focuses on, found when the scene is set up rather than by
+/// measuring the focus distance by hand.
+#[derive(Copy, Clone)]
+pub enum Focus {
+    /// A point in world space.
+    Point(Vec3),
+    /// The first surface along the centre of the view.
+    Center,
+    /// The first surface seen at image coordinates `(s, t)`, in `[0, 1]`
+    /// with `t` pointing up, as passed to `get_ray`.
+    Pixel(f64, f64),
+}
+
+impl Camera {
+    /// Moves the plane in focus to pass through the target, keeping the
+    /// field of view. The focus distance is unchanged if no surface is hit.
+    pub fn focus(self, focus: Focus, world: &World) -> Camera {
+        let target = match focus {
+            Focus::Point(p) => Some(p),
+            Focus::Center => self.pinhole_hit(0.5, 0.5, world),
+            Focus::Pixel(s, t) => self.pinhole_hit(s, t, world),
+        };
+        match target {
+            Some(p) => {
+                let distance = Vec3::dot(&(self.origin - p), &self.w);
+                self.with_focus_dist(distance)
+            }
+            None => self,
+        }
+    }
+
+    /// The first hit along the ray through the centre of the lens.
+    fn pinhole_hit(&self, s: f64, t: f64, world: &World) -> Option<Vec3> {
+        let direction = self.lower_left + self.horizontal * s + self.vertical * t - self.origin;
+        let ray = Ray::new(self.origin, direction);
+        world.hit(&ray, 0.0001, f64::MAX).map(|hrec| hrec.p)
+    }
+
+    /// Scales the image plane to lie `distance` in front of the lens.
+    fn with_focus_dist(self, distance: f64) -> Camera {
+        if distance <= 0. {
+            return self;
+        }
+        let k = distance / Vec3::dot(&(self.origin - self.lower_left), &self.w);
+        Camera {
+            lower_left: self.origin + (self.lower_left - self.origin) * k,
+            horizontal: self.horizontal * k,
+            vertical: self.vertical * k,
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hitable::surfaces::sphere::Sphere;
+    use material::common::lambertian::Lambertian;
+
+    fn focus_dist(camera: &Camera) -> f64 {
+        Vec3::dot(&(camera.origin - camera.lower_left), &camera.w)
+    }
+
+    #[test]
+    fn focuses_on_the_first_hit() {
+        let material = Lambertian::new(Vec3::new(0.5, 0.5, 0.5));
+        let world = World::new(vec![Box::new(Sphere::new(Vec3::new(0., 0., 0.), 1., material))]);
+        let camera = || Camera::new(Vec3::new(0., 0., 5.), Vec3::new(0., 0., 0.), Vec3::new(0., 1., 0.),
+                                    40., 1.5, 0.1, 1.);
+        let width = camera().horizontal.length();
+
+        let centre = camera().focus(Focus::Center, &world);
+        assert!((focus_dist(&centre) - 4.).abs() < 1e-9);
+        assert!((centre.horizontal.length() - 4. * width).abs() < 1e-9);
+        let pixel = camera().focus(Focus::Pixel(0.5, 0.5), &world);
+        assert!((focus_dist(&pixel) - 4.).abs() < 1e-9);
+
+        let point = camera().focus(Focus::Point(Vec3::new(3., 1., -2.)), &world);
+        assert!((focus_dist(&point) - 7.).abs() < 1e-9);
+
+        let missed = camera().focus(Focus::Pixel(0., 1.), &world);
+        assert!((focus_dist(&missed) - 1.).abs() < 1e-9);
+        let behind = camera().focus(Focus::Point(Vec3::new(0., 0., 6.)), &world);
+        assert!((focus_dist(&behind) - 1.).abs() < 1e-9);
+    }
+}
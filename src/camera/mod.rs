@@ -2,7 +2,39 @@ use std::f64::consts::PI;
 
 use vec3::Vec3;
 use ray::Ray;
-use common::random_in_unit_disc;
+use common::rand;
+
+pub mod orthographic;
+pub mod equirectangular;
+pub mod cube_map;
+pub mod fisheye;
+pub mod stereo;
+pub mod physical;
+pub mod aperture;
+pub mod focus;
+
+use self::physical::PhysicalSettings;
+use self::aperture::Aperture;
+
+/// Maps a point `(s, t)` on the image, with both coordinates in `[0, 1]`
+/// and `t` pointing up, to a ray leaving the camera.
+pub trait Projection {
+    fn get_ray(&self, s: f64, t: f64) -> Ray;
+
+    /// The factor scene radiance is multiplied by to get pixel values.
+    fn exposure(&self) -> f64 {
+        1.
+    }
+}
+
+/// The orthonormal frame `(u, v, w)` of a camera at `lookfrom` facing
+/// `lookat`, with `u` to the right, `v` up and `w` backwards.
+fn basis(lookfrom: Vec3, lookat: Vec3, vup: Vec3) -> (Vec3, Vec3, Vec3) {
+    let w = (lookfrom - lookat).unit_vector();
+    let u = Vec3::cross(&vup, &w).unit_vector();
+    let v = Vec3::cross(&w, &u);
+    (u, v, w)
+}
 
 pub struct Camera {
    lower_left: Vec3,
@@ -13,6 +45,10 @@ pub struct Camera {
    v: Vec3,
    w: Vec3,
    lens_radius: f64,
+   aperture: Aperture,
+   shutter_open: f64,
+   shutter_close: f64,
+   exposure: f64,
 }
 
 impl Camera {
@@ -22,9 +58,7 @@ impl Camera {
         let half_height = (theta/2.).tan();
         let half_width = aspect * half_height;
 
-        let w = (lookfrom - lookat).unit_vector();
-        let u = Vec3::cross(&vup, &w).unit_vector();
-        let v = Vec3::cross(&w, &u);
+        let (u, v, w) = basis(lookfrom, lookat, vup);
 
         let origin = lookfrom;
         let lower_left = origin - u * half_width * focus_dist - v * half_height * focus_dist - w * focus_dist;
@@ -40,15 +74,63 @@ impl Camera {
             v,
             w,
             lens_radius,
+            aperture: Aperture::Circle,
+            shutter_open: 0.,
+            shutter_close: 0.,
+            exposure: 1.,
+        }
+    }
+
+    /// A camera matching the field of view, depth of field, shutter and
+    /// exposure of a real camera, with the scene measured in meters and the
+    /// shutter opening at time zero.
+    pub fn physical(lookfrom: Vec3, lookat: Vec3, vup: Vec3, focus_dist: f64, settings: &PhysicalSettings) -> Camera {
+        let camera = Camera::new(lookfrom, lookat, vup, settings.vfov(), settings.aspect(), settings.aperture(),
+                                 focus_dist);
+        Camera {
+            exposure: settings.exposure(),
+            ..camera.with_shutter(0., settings.shutter())
         }
     }
 
-    pub fn get_ray(&self, s: f64, t: f64) -> Ray {
-        let rd = random_in_unit_disc() * self.lens_radius;
+    /// Spreads the rays over the time the shutter is open, so that moving
+    /// objects are blurred.
+    pub fn with_shutter(self, open: f64, close: f64) -> Camera {
+        Camera {
+            shutter_open: open,
+            shutter_close: close,
+            ..self
+        }
+    }
+
+    /// Moves the lens by `offset` while keeping the image plane in place,
+    /// which shifts the view without turning it.
+    pub fn shifted(self, offset: Vec3) -> Camera {
+        Camera {
+            origin: self.origin + offset,
+            ..self
+        }
+    }
+
+    /// Sets the shape of the lens opening, whose size remains given by the
+    /// aperture diameter.
+    pub fn with_aperture(self, aperture: Aperture) -> Camera {
+        Camera { aperture, ..self }
+    }
+}
+
+impl Projection for Camera {
+    fn get_ray(&self, s: f64, t: f64) -> Ray {
+        let rd = self.aperture.sample() * self.lens_radius;
         let offset = self.u * rd.e[0] + self.v * rd.e[1];
+        let time = self.shutter_open + rand() * (self.shutter_close - self.shutter_open);
         Ray::new(self.origin + offset, self.lower_left
                               + (self.horizontal * s)
                               + (self.vertical * t)
-                              - self.origin - offset)
+                              - self.origin - offset).with_time(time)
+    }
+
+    fn exposure(&self) -> f64 {
+        self.exposure
     }
 }
@@ -0,0 +1,50 @@
+use vec3::Vec3;
+use ray::Ray;
+use camera::{Projection, basis};
+
+/// A camera with parallel rays, for technical and isometric views, framing
+/// a view `width` by `height` across.
+pub struct Orthographic {
+    lower_left: Vec3,
+    horizontal: Vec3,
+    vertical: Vec3,
+    direction: Vec3,
+}
+
+impl Orthographic {
+    pub fn new(lookfrom: Vec3, lookat: Vec3, vup: Vec3, width: f64, height: f64) -> Orthographic {
+        let (u, v, w) = basis(lookfrom, lookat, vup);
+
+        let horizontal = u * width;
+        let vertical = v * height;
+        Orthographic {
+            lower_left: lookfrom - horizontal * 0.5 - vertical * 0.5,
+            horizontal,
+            vertical,
+            direction: -w,
+        }
+    }
+}
+
+impl Projection for Orthographic {
+    fn get_ray(&self, s: f64, t: f64) -> Ray {
+        Ray::new(self.lower_left + self.horizontal * s + self.vertical * t, self.direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rays_are_parallel_across_the_film() {
+        let camera = Orthographic::new(Vec3::new(0., 0., 5.), Vec3::new(0., 0., 0.), Vec3::new(0., 1., 0.), 4., 2.);
+        let centre = camera.get_ray(0.5, 0.5);
+        assert_eq!(centre.origin.e, [0., 0., 5.]);
+        assert_eq!(centre.direction.e, [0., 0., -1.]);
+        let corner = camera.get_ray(0., 0.);
+        assert_eq!(corner.origin.e, [-2., -1., 5.]);
+        assert_eq!(corner.direction.e, [0., 0., -1.]);
+        assert_eq!(camera.get_ray(1., 1.).origin.e, [2., 1., 5.]);
+    }
+}
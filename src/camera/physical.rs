@@ -0,0 +1,100 @@
+use spectrum::unit_luminance;
+
+/// The settings of a real camera, with the scene measured in meters.
+#[derive(Copy, Clone)]
+pub struct PhysicalSettings {
+    focal_length: f64,
+    sensor_width: f64,
+    sensor_height: f64,
+    f_number: f64,
+    shutter_speed: f64,
+    iso: f64,
+}
+
+impl PhysicalSettings {
+    /// A lens with `focal_length` in millimeters on a full frame sensor, at
+    /// f/8, 1/125s and ISO 100.
+    pub fn new(focal_length: f64) -> PhysicalSettings {
+        PhysicalSettings {
+            focal_length,
+            sensor_width: 36.,
+            sensor_height: 24.,
+            f_number: 8.,
+            shutter_speed: 1. / 125.,
+            iso: 100.,
+        }
+    }
+
+    /// The size of the sensor in millimeters.
+    pub fn sensor(self, width: f64, height: f64) -> PhysicalSettings {
+        PhysicalSettings { sensor_width: width, sensor_height: height, ..self }
+    }
+
+    pub fn f_number(self, f_number: f64) -> PhysicalSettings {
+        PhysicalSettings { f_number, ..self }
+    }
+
+    /// The time the shutter is open, in seconds.
+    pub fn shutter_speed(self, shutter_speed: f64) -> PhysicalSettings {
+        PhysicalSettings { shutter_speed, ..self }
+    }
+
+    pub fn iso(self, iso: f64) -> PhysicalSettings {
+        PhysicalSettings { iso, ..self }
+    }
+
+    /// The vertical field of view in degrees.
+    pub fn vfov(&self) -> f64 {
+        (2. * (self.sensor_height / (2. * self.focal_length)).atan()).to_degrees()
+    }
+
+    pub fn aspect(&self) -> f64 {
+        self.sensor_width / self.sensor_height
+    }
+
+    /// The diameter of the entrance pupil in meters.
+    pub fn aperture(&self) -> f64 {
+        self.focal_length / self.f_number * 1e-3
+    }
+
+    pub fn shutter(&self) -> f64 {
+        self.shutter_speed
+    }
+
+    /// The factor mapping scene radiance to pixel values, such that a
+    /// luminance that would saturate the sensor maps to one. Follows the
+    /// saturation based sensitivity of ISO 12232, as in Lagarde and de
+    /// Rousiers (2014).
+    pub fn exposure(&self) -> f64 {
+        let ev100 = (self.f_number * self.f_number / self.shutter_speed * 100. / self.iso).log2();
+        unit_luminance() / (1.2 * 2_f64.powf(ev100))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_ratio(a: f64, b: f64, ratio: f64) {
+        assert!((a / b - ratio).abs() < 1e-9, "{} / {} != {}", a, b, ratio);
+    }
+
+    #[test]
+    fn exposure_follows_the_exposure_triangle() {
+        let base = PhysicalSettings::new(50.);
+        let exposure = base.exposure();
+        assert_ratio(base.f_number(8. / 2_f64.sqrt()).exposure(), exposure, 2.);
+        assert_ratio(base.f_number(16.).exposure(), exposure, 0.25);
+        assert_ratio(base.shutter_speed(2. / 125.).exposure(), exposure, 2.);
+        assert_ratio(base.iso(200.).exposure(), exposure, 2.);
+    }
+
+    #[test]
+    fn lens_matches_the_sensor() {
+        let settings = PhysicalSettings::new(50.).f_number(2.);
+        assert!((settings.vfov() - 2. * (12_f64 / 50.).atan().to_degrees()).abs() < 1e-9);
+        assert_eq!(settings.aspect(), 1.5);
+        assert!((settings.aperture() - 0.025).abs() < 1e-12);
+        assert_eq!(settings.sensor(24., 24.).aspect(), 1.);
+    }
+}
@@ -0,0 +1,167 @@
+use std::f64::consts::PI;
+
+use vec3::Vec3;
+use ray::Ray;
+use camera::{Camera, Projection, basis};
+
+/// One of the eyes of a stereo camera.
+#[derive(Copy, Clone)]
+pub enum Eye {
+    Left,
+    Right,
+}
+
+/// An eye of an omnidirectional stereo panorama: every column of the
+/// equirectangular image is seen from a point on a circle whose diameter is
+/// the interocular distance, so the stereo effect holds in all directions.
+struct OmnidirectionalEye {
+    origin: Vec3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    offset: f64,
+}
+
+impl Projection for OmnidirectionalEye {
+    fn get_ray(&self, s: f64, t: f64) -> Ray {
+        let longitude = (s - 0.5) * 2. * PI;
+        let latitude = (t - 0.5) * PI;
+        let direction = self.u * (latitude.cos() * longitude.sin())
+                        + self.v * latitude.sin()
+                        - self.w * (latitude.cos() * longitude.cos());
+        let right = self.u * longitude.cos() + self.w * longitude.sin();
+        Ray::new(self.origin + right * self.offset, direction)
+    }
+}
+
+/// A pair of cameras for stereoscopic rendering, with the eyes
+/// `interocular` apart around `lookfrom`. Each eye can be rendered to its
+/// own image, or both to one image with the left eye on top and the right
+/// eye below.
+pub struct StereoCamera {
+    left: Box<dyn Projection + Sync>,
+    right: Box<dyn Projection + Sync>,
+}
+
+impl StereoCamera {
+    /// Eyes with parallel axes whose views are shifted to coincide at the
+    /// `convergence` distance, giving objects there zero parallax without
+    /// distorting the image. `aspect` is that of each eye.
+    pub fn parallel(lookfrom: Vec3, lookat: Vec3, vup: Vec3, vfov: f64, aspect: f64, interocular: f64,
+                    convergence: f64) -> StereoCamera {
+        let (u, _, _) = basis(lookfrom, lookat, vup);
+        let eye = |offset: f64| {
+            let camera = Camera::new(lookfrom, lookat, vup, vfov, aspect, 0., convergence);
+            Box::new(camera.shifted(u * offset))
+        };
+        StereoCamera {
+            left: eye(-interocular / 2.),
+            right: eye(interocular / 2.),
+        }
+    }
+
+    /// Eyes rotated inwards to look at the point `convergence` away along
+    /// the view direction, as with two physical cameras. This introduces
+    /// vertical parallax towards the corners of the image.
+    pub fn toe_in(lookfrom: Vec3, lookat: Vec3, vup: Vec3, vfov: f64, aspect: f64, interocular: f64,
+                  convergence: f64) -> StereoCamera {
+        let (u, _, w) = basis(lookfrom, lookat, vup);
+        let target = lookfrom - w * convergence;
+        let eye = |offset: f64| {
+            let position = lookfrom + u * offset;
+            let focus_dist = (target - position).length();
+            Box::new(Camera::new(position, target, vup, vfov, aspect, 0., focus_dist))
+        };
+        StereoCamera {
+            left: eye(-interocular / 2.),
+            right: eye(interocular / 2.),
+        }
+    }
+
+    /// A 360 degree equirectangular panorama for each eye, for viewing in a
+    /// headset.
+    pub fn omnidirectional(lookfrom: Vec3, lookat: Vec3, vup: Vec3, interocular: f64) -> StereoCamera {
+        let (u, v, w) = basis(lookfrom, lookat, vup);
+        let eye = |offset: f64| {
+            Box::new(OmnidirectionalEye {
+                origin: lookfrom,
+                u,
+                v,
+                w,
+                offset,
+            })
+        };
+        StereoCamera {
+            left: eye(-interocular / 2.),
+            right: eye(interocular / 2.),
+        }
+    }
+
+    /// The projection of a single eye, to render it to its own image.
+    pub fn eye(&self, eye: Eye) -> &(dyn Projection + Sync) {
+        match eye {
+            Eye::Left => &*self.left,
+            Eye::Right => &*self.right,
+        }
+    }
+}
+
+/// Renders both eyes to one image, the left eye in the top half.
+impl Projection for StereoCamera {
+    fn get_ray(&self, s: f64, t: f64) -> Ray {
+        if t >= 0.5 {
+            self.left.get_ray(s, t * 2. - 1.)
+        } else {
+            self.right.get_ray(s, t * 2.)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-9, "{:?} != {:?}", a.e, b.e);
+    }
+
+    #[test]
+    fn omnidirectional_eyes_are_half_interocular_apart() {
+        let lookfrom = Vec3::new(1., 2., 3.);
+        let camera = StereoCamera::omnidirectional(lookfrom, Vec3::new(1., 2., 0.), Vec3::new(0., 1., 0.), 0.064);
+        let (left, right) = (camera.eye(Eye::Left), camera.eye(Eye::Right));
+        assert_close(left.get_ray(0.5, 0.5).origin, Vec3::new(0.968, 2., 3.));
+        assert_close(right.get_ray(0.5, 0.5).origin, Vec3::new(1.032, 2., 3.));
+        // Looking along +x, the right eye is towards +z.
+        assert_close(right.get_ray(0.75, 0.5).origin, Vec3::new(1., 2., 3.032));
+        for i in 0..16 {
+            let s = i as f64 / 16.;
+            let (l, r) = (left.get_ray(s, 0.3), right.get_ray(s, 0.3));
+            assert!(((l.origin - lookfrom).length() - 0.032).abs() < 1e-9);
+            assert!(((r.origin - lookfrom).length() - 0.032).abs() < 1e-9);
+            assert_close(l.origin + r.origin, lookfrom * 2.);
+            assert!(Vec3::dot(&(r.origin - l.origin), &l.direction).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn eyes_converge_at_the_convergence_distance() {
+        let lookfrom = Vec3::new(0., 0., 5.);
+        let target = Vec3::new(0., 0., 2.);
+        let vup = Vec3::new(0., 1., 0.);
+        let cameras = [
+            StereoCamera::parallel(lookfrom, Vec3::new(0., 0., 0.), vup, 40., 1.5, 0.1, 3.),
+            StereoCamera::toe_in(lookfrom, Vec3::new(0., 0., 0.), vup, 40., 1.5, 0.1, 3.),
+        ];
+        for camera in cameras.iter() {
+            let left = camera.eye(Eye::Left).get_ray(0.5, 0.5);
+            let right = camera.eye(Eye::Right).get_ray(0.5, 0.5);
+            assert_close(left.origin, Vec3::new(-0.05, 0., 5.));
+            assert_close(right.origin, Vec3::new(0.05, 0., 5.));
+            assert!(Vec3::cross(&(target - left.origin), &left.direction).length() < 1e-9);
+            assert!(Vec3::cross(&(target - right.origin), &right.direction).length() < 1e-9);
+            assert_close(camera.get_ray(0.5, 0.75).origin, left.origin);
+            assert_close(camera.get_ray(0.5, 0.25).origin, right.origin);
+        }
+    }
+}
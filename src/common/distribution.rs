@@ -0,0 +1,144 @@
+/// A piecewise constant distribution over `[0, 1)`, sampled by inverting its
+/// cumulative distribution.
+pub struct Distribution1D {
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    integral: f64,
+}
+
+impl Distribution1D {
+    /// Creates a distribution proportional to the non-negative `func`, which
+    /// is uniform if `func` is zero everywhere. `func` must not be empty.
+    pub fn new(func: Vec<f64>) -> Distribution1D {
+        assert!(!func.is_empty(), "empty distribution");
+        let n = func.len();
+        let mut cdf = Vec::with_capacity(n + 1);
+        cdf.push(0.);
+        for (i, f) in func.iter().enumerate() {
+            let previous = cdf[i];
+            cdf.push(previous + f / n as f64);
+        }
+        let integral = cdf[n];
+        for (i, c) in cdf.iter_mut().enumerate() {
+            *c = if integral > 0. { *c / integral } else { i as f64 / n as f64 };
+        }
+        Distribution1D {
+            func,
+            cdf,
+            integral,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    pub fn integral(&self) -> f64 {
+        self.integral
+    }
+
+    /// Maps `u` to a point in `[0, 1)`, returning the point, its density and
+    /// the index of the segment it lies in.
+    pub fn sample(&self, u: f64) -> (f64, f64, usize) {
+        let n = self.count();
+        let i = (self.cdf.partition_point(|&c| c <= u) - 1).min(n - 1);
+        let width = self.cdf[i + 1] - self.cdf[i];
+        let offset = if width > 0. { (u - self.cdf[i]) / width } else { 0. };
+        ((i as f64 + offset) / n as f64, self.pdf_at(i), i)
+    }
+
+    /// The density at a point in segment `i`.
+    pub fn pdf_at(&self, i: usize) -> f64 {
+        if self.integral > 0. { self.func[i] / self.integral } else { 1. }
+    }
+
+    pub fn pdf(&self, x: f64) -> f64 {
+        self.pdf_at(((x * self.count() as f64) as usize).min(self.count() - 1))
+    }
+}
+
+/// A piecewise constant distribution over `[0, 1)^2`, given as a non-empty
+/// grid of `nu` by `nv` values with `u` varying fastest.
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    pub fn new(func: &[f64], nu: usize, nv: usize) -> Distribution2D {
+        assert!(nu > 0 && nv > 0 && func.len() >= nu * nv, "invalid distribution size");
+        let conditional: Vec<Distribution1D> = func.chunks(nu)
+                                                   .take(nv)
+                                                   .map(|row| Distribution1D::new(row.to_vec()))
+                                                   .collect();
+        let marginal = Distribution1D::new(conditional.iter().map(|d| d.integral()).collect());
+        Distribution2D {
+            conditional,
+            marginal,
+        }
+    }
+
+    /// Maps `(u1, u2)` to a point, returning it with its density.
+    pub fn sample(&self, u1: f64, u2: f64) -> ((f64, f64), f64) {
+        let (v, pdf_v, row) = self.marginal.sample(u2);
+        let (u, pdf_u, _) = self.conditional[row].sample(u1);
+        ((u, v), pdf_u * pdf_v)
+    }
+
+    pub fn pdf(&self, u: f64, v: f64) -> f64 {
+        let row = ((v * self.marginal.count() as f64) as usize).min(self.marginal.count() - 1);
+        self.conditional[row].pdf(u) * self.marginal.pdf_at(row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_proportionally() {
+        let distribution = Distribution1D::new(vec![1., 3.]);
+        assert_eq!(distribution.integral(), 2.);
+        assert_eq!(distribution.sample(0.), (0., 0.5, 0));
+        assert_eq!(distribution.sample(0.125), (0.25, 0.5, 0));
+        assert_eq!(distribution.sample(0.625), (0.75, 1.5, 1));
+        assert_eq!(distribution.pdf(0.9), 1.5);
+        assert_eq!(distribution.sample(1.).2, 1);
+    }
+
+    #[test]
+    fn zero_function_is_uniform() {
+        let distribution = Distribution1D::new(vec![0., 0., 0., 0.]);
+        assert_eq!(distribution.sample(0.375), (0.375, 1., 1));
+        assert_eq!(distribution.pdf(0.9), 1.);
+    }
+
+    #[test]
+    fn skips_empty_segments() {
+        let distribution = Distribution1D::new(vec![0., 1., 0.]);
+        for &u in [0., 0.5, 0.999].iter() {
+            let (x, pdf, i) = distribution.sample(u);
+            assert_eq!(i, 1);
+            assert!((1. / 3.0..2. / 3.0).contains(&x));
+            assert_eq!(pdf, 3.);
+        }
+    }
+
+    #[test]
+    fn joint_density_matches_samples() {
+        let func = [1., 2., 0., 5.];
+        let distribution = Distribution2D::new(&func, 2, 2);
+        for &(u1, u2) in [(0.1, 0.1), (0.9, 0.2), (0.5, 0.9), (0.2, 0.6)].iter() {
+            let ((u, v), pdf) = distribution.sample(u1, u2);
+            assert!((distribution.pdf(u, v) - pdf).abs() < 1e-12);
+            let i = (u * 2.) as usize + 2 * (v * 2.) as usize;
+            assert!((pdf - func[i] / 2.).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_empty_distribution() {
+        Distribution2D::new(&[], 0, 0);
+    }
+}
@@ -1,5 +1,9 @@
 extern crate rand;
 
+use std::f64::consts::PI;
+
+pub mod distribution;
+
 use self::rand::{OsRng,Rng};
 
 use vec3::Vec3;
@@ -48,3 +52,176 @@ pub fn random_in_unit_disc() -> Vec3 {
         }
     }
 }
+
+pub fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let cos_i = cos_i.abs().min(1.);
+    let sin_t2 = (1. - cos_i * cos_i) / (eta * eta);
+    if sin_t2 >= 1. {
+        return 1.;
+    }
+    let cos_t = (1. - sin_t2).sqrt();
+    let rs = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    let rp = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    0.5 * (rs * rs + rp * rp)
+}
+
+pub fn orthonormal_basis(n: &Vec3) -> (Vec3, Vec3) {
+    let sign = 1_f64.copysign(n.e[2]);
+    let a = -(sign + n.e[2]).recip();
+    let b = n.e[0] * n.e[1] * a;
+    let s = Vec3::new(1. + sign * n.e[0] * n.e[0] * a, sign * b, -sign * n.e[0]);
+    let t = Vec3::new(b, sign + n.e[1] * n.e[1] * a, -n.e[1]);
+    (s, t)
+}
+
+/// The GGX roughness at or below which a microfacet lobe is treated as a
+/// perfect mirror: too narrow to be evaluated towards sampled lights.
+pub const SPECULAR_ALPHA: f64 = 1e-3;
+
+pub fn sample_ggx(n: &Vec3, alpha: f64) -> Vec3 {
+    let xi = rand();
+    let phi = 2. * PI * rand();
+    let tan2 = alpha * alpha * xi / (1. - xi);
+    let cos_theta = (1. + tan2).sqrt().recip();
+    let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+    to_world(&Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta), n)
+}
+
+pub fn ggx_d(m: &Vec3, n: &Vec3, alpha: f64) -> f64 {
+    let cos_theta = Vec3::dot(m, n);
+    if cos_theta <= 0. {
+        return 0.;
+    }
+    let cos2 = cos_theta * cos_theta;
+    let tan2 = (1. - cos2) / cos2;
+    let alpha2 = alpha * alpha;
+    alpha2 / (PI * cos2 * cos2 * (alpha2 + tan2).powi(2))
+}
+
+pub fn ggx_g1(v: &Vec3, m: &Vec3, n: &Vec3, alpha: f64) -> f64 {
+    let cos_v = Vec3::dot(v, n);
+    if Vec3::dot(v, m) * cos_v <= 0. {
+        return 0.;
+    }
+    let cos2 = cos_v * cos_v;
+    let tan2 = (1. - cos2) / cos2;
+    2. / (1. + (1. + alpha * alpha * tan2).sqrt())
+}
+
+pub fn beer_lambert(absorption: &Vec3, distance: f64) -> Vec3 {
+    Vec3::new((-absorption.e[0] * distance).exp(),
+              (-absorption.e[1] * distance).exp(),
+              (-absorption.e[2] * distance).exp())
+}
+
+/// Luminance of a linear RGB colour, with the Rec. 709 weights.
+pub fn luminance(c: &Vec3) -> f64 {
+    0.2126 * c.e[0] + 0.7152 * c.e[1] + 0.0722 * c.e[2]
+}
+
+/// A cosine weighted direction around the `z` axis.
+pub fn random_cosine_direction() -> Vec3 {
+    let r = rand().sqrt();
+    let phi = 2. * PI * rand();
+    Vec3::new(r * phi.cos(), r * phi.sin(), (1. - r * r).max(0.).sqrt())
+}
+
+pub fn cosine_hemisphere_pdf(cosine: f64) -> f64 {
+    cosine.max(0.) / PI
+}
+
+/// A uniformly distributed direction around the `z` axis.
+pub fn random_hemisphere_direction() -> Vec3 {
+    let z = rand();
+    let r = (1. - z * z).max(0.).sqrt();
+    let phi = 2. * PI * rand();
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+pub fn uniform_hemisphere_pdf() -> f64 {
+    0.5 / PI
+}
+
+/// Transforms a direction from the local frame around the `z` axis to the
+/// frame around `n`.
+pub fn to_world(local: &Vec3, n: &Vec3) -> Vec3 {
+    let (s, t) = orthonormal_basis(n);
+    s * local.e[0] + t * local.e[1] + *n * local.e[2]
+}
+
+pub fn sample_cosine_hemisphere(n: &Vec3) -> Vec3 {
+    to_world(&random_cosine_direction(), n)
+}
+
+pub fn sample_uniform_hemisphere(n: &Vec3) -> Vec3 {
+    to_world(&random_hemisphere_direction(), n)
+}
+
+pub fn random_unit_vector() -> Vec3 {
+    random_in_unit_sphere().unit_vector()
+}
+
+/// The Henyey-Greenstein phase function for the cosine between the incident
+/// and scattered directions, normalized over the sphere.
+pub fn henyey_greenstein(cosine: f64, g: f64) -> f64 {
+    let denominator = 1. + g * g - 2. * g * cosine;
+    (1. - g * g) / (4. * PI * denominator * denominator.sqrt())
+}
+
+/// Samples a direction scattered from `direction` according to the
+/// Henyey-Greenstein phase function, with `g > 0` favouring forward
+/// scattering.
+pub fn sample_henyey_greenstein(direction: &Vec3, g: f64) -> Vec3 {
+    let xi = rand();
+    let cos_theta = if g.abs() < 1e-3 {
+        1. - 2. * xi
+    } else {
+        let s = (1. - g * g) / (1. - g + 2. * g * xi);
+        (1. + g * g - s * s) / (2. * g)
+    };
+    let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+    let phi = 2. * PI * rand();
+    to_world(&Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta), &direction.unit_vector())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fresnel_at_normal_incidence() {
+        let eta: f64 = 1.5;
+        let r0 = ((eta - 1.) / (eta + 1.)).powi(2);
+        assert!((fresnel_dielectric(1., eta) - r0).abs() < 1e-12);
+        assert!((fresnel_dielectric(-1., eta) - r0).abs() < 1e-12);
+        assert!((schlick(1., eta) - r0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn fresnel_is_reciprocal() {
+        let eta: f64 = 1.5;
+        for &cos_i in [0.1_f64, 0.5, 0.9].iter() {
+            let cos_t = (1. - (1. - cos_i * cos_i) / (eta * eta)).sqrt();
+            assert!((fresnel_dielectric(cos_i, eta) - fresnel_dielectric(cos_t, 1. / eta)).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn fresnel_reflects_totally() {
+        assert_eq!(fresnel_dielectric(0., 1.5), 1.);
+        assert_eq!(fresnel_dielectric(0.5, 1. / 1.5), 1.);
+    }
+
+    #[test]
+    fn orthonormal_basis_is_orthonormal() {
+        for n in [Vec3::new(0., 0., 1.), Vec3::new(0., 0., -1.), Vec3::new(1., 2., -3.).unit_vector()].iter() {
+            let (s, t) = orthonormal_basis(n);
+            for &(a, b) in [(&s, &t), (&s, n), (&t, n)].iter() {
+                assert!(Vec3::dot(a, b).abs() < 1e-12);
+            }
+            for v in [s, t].iter() {
+                assert!((v.length() - 1.).abs() < 1e-12);
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use std::f64::consts::PI;
+
+use vec3::Vec3;
+use common::{rand, luminance};
+use common::distribution::Distribution2D;
+use light::{Light, LightSample};
+use texture::image::Image;
+
+/// Image based lighting from an equirectangular HDR image surrounding the
+/// scene. It is a light rather than an `Environment` so that the integrator
+/// can sample the bright parts of the image, such as the sun, directly.
+pub struct EnvironmentMap {
+    image: Image,
+    rotation: f64,
+    intensity: f64,
+    distribution: Distribution2D,
+}
+
+impl EnvironmentMap {
+    /// Creates a light from an image whose top row is the zenith and whose
+    /// centre column faces the `-z` axis.
+    pub fn new(image: Image) -> EnvironmentMap {
+        let (width, height) = (image.width(), image.height());
+        let mut weights = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let sin_theta = (PI * (y as f64 + 0.5) / height as f64).sin();
+            for x in 0..width {
+                weights.push(luminance(&image.pixel(x, y)) * sin_theta);
+            }
+        }
+        // Bilinear filtering spreads radiance over neighbouring pixels, so
+        // every pixel keeps a small chance of being sampled.
+        let floor = weights.iter().sum::<f64>() / weights.len() as f64 * 1e-3;
+        for w in weights.iter_mut() {
+            *w += floor;
+        }
+
+        EnvironmentMap {
+            distribution: Distribution2D::new(&weights, width, height),
+            image,
+            rotation: 0.,
+            intensity: 1.,
+        }
+    }
+
+    /// Rotates the environment by `degrees` about the vertical axis.
+    pub fn rotation(self, degrees: f64) -> EnvironmentMap {
+        EnvironmentMap { rotation: degrees.to_radians(), ..self }
+    }
+
+    /// Scales the radiance of the image.
+    pub fn intensity(self, intensity: f64) -> EnvironmentMap {
+        EnvironmentMap { intensity, ..self }
+    }
+
+    /// The image coordinates of a direction, with `t` pointing down.
+    fn coordinates(&self, direction: &Vec3) -> (f64, f64) {
+        let d = direction.unit_vector();
+        let phi = d.e[0].atan2(-d.e[2]) + self.rotation;
+        let u = (0.5 + phi / (2. * PI)).rem_euclid(1.);
+        let t = d.e[1].clamp(-1., 1.).acos() / PI;
+        (u, t)
+    }
+
+    fn direction(&self, u: f64, t: f64) -> Vec3 {
+        let phi = (u - 0.5) * 2. * PI - self.rotation;
+        let theta = t * PI;
+        Vec3::new(theta.sin() * phi.sin(), theta.cos(), -theta.sin() * phi.cos())
+    }
+}
+
+impl Light for EnvironmentMap {
+    fn sample(&self, _p: &Vec3) -> Option<LightSample> {
+        let ((u, t), pdf) = self.distribution.sample(rand(), rand());
+        let sin_theta = (t * PI).sin();
+        if pdf <= 0. || sin_theta <= 0. {
+            return None;
+        }
+        let pdf = pdf / (2. * PI * PI * sin_theta);
+        let direction = self.direction(u, t);
+        Some(LightSample {
+            direction,
+            distance: f64::MAX,
+            radiance: self.emitted(&direction) / pdf,
+            spectrum: None,
+        })
+    }
+
+    fn emitted(&self, direction: &Vec3) -> Vec3 {
+        let (u, t) = self.coordinates(direction);
+        self.image.sample(u, 1. - t) * self.intensity
+    }
+}
@@ -0,0 +1,32 @@
+use vec3::Vec3;
+
+pub mod map;
+pub mod preetham;
+
+/// The radiance arriving from infinitely far away along rays that leave the
+/// scene without hitting anything.
+pub trait Environment {
+    fn radiance(&self, direction: &Vec3) -> Vec3;
+}
+
+/// A sky blending from one colour at the nadir to another at the zenith.
+pub struct Gradient {
+    bottom: Vec3,
+    top: Vec3,
+}
+
+impl Gradient {
+    pub fn new(bottom: Vec3, top: Vec3) -> Gradient {
+        Gradient {
+            bottom,
+            top,
+        }
+    }
+}
+
+impl Environment for Gradient {
+    fn radiance(&self, direction: &Vec3) -> Vec3 {
+        let t = (direction.unit_vector().e[1] + 1.) * 0.5;
+        self.bottom * (1. - t) + self.top * t
+    }
+}
@@ -0,0 +1,211 @@
+use std::f64::consts::PI;
+
+use vec3::Vec3;
+use environment::Environment;
+use light::Sun;
+use spectrum::{cie_xyz, planck, xyz_to_rgb, unit_luminance, LAMBDA_MIN, LAMBDA_MAX};
+
+/// Scale from the model's luminance in kcd/m^2 to scene radiance, chosen so
+/// that a white diffuse surface under the midday sun comes out around one.
+const SCALE: f64 = 0.05;
+
+/// Luminance of the sun seen from outside the atmosphere, in kcd/m^2.
+const SUN_LUMINANCE: f64 = 1.6e6;
+
+const SUN_TEMPERATURE: f64 = 5778.;
+
+/// Angular radius of the sun disk in radians.
+const SUN_RADIUS: f64 = 0.00465;
+
+/// The parameters of the Perez sky luminance distribution for one of `Y`,
+/// `x` and `y`.
+#[derive(Copy, Clone)]
+struct Perez {
+    a: f64,
+    b: f64,
+    c: f64,
+    d: f64,
+    e: f64,
+}
+
+impl Perez {
+    fn new(turbidity: f64, coefficients: [[f64; 2]; 5]) -> Perez {
+        let p = |i: usize| coefficients[i][0] * turbidity + coefficients[i][1];
+        Perez {
+            a: p(0),
+            b: p(1),
+            c: p(2),
+            d: p(3),
+            e: p(4),
+        }
+    }
+
+    /// Relative luminance at zenith angle `theta` and angle `gamma` from the
+    /// sun.
+    fn eval(&self, theta: f64, gamma: f64) -> f64 {
+        let cos_theta = theta.cos().max(0.01);
+        (1. + self.a * (self.b / cos_theta).exp())
+            * (1. + self.c * (self.d * gamma).exp() + self.e * gamma.cos().powi(2))
+    }
+}
+
+/// Zenith chromaticity from a polynomial in turbidity and sun zenith angle.
+fn zenith_chromaticity(turbidity: f64, theta: f64, m: [[f64; 4]; 3]) -> f64 {
+    let t = [turbidity * turbidity, turbidity, 1.];
+    let s = [theta.powi(3), theta * theta, theta, 1.];
+    let mut value = 0.;
+    for (i, row) in m.iter().enumerate() {
+        for (j, c) in row.iter().enumerate() {
+            value += t[i] * c * s[j];
+        }
+    }
+    value
+}
+
+/// The analytic daylight model of Preetham, Shirley and Smits (1999), giving
+/// the clear sky radiance for a sun position and the haziness of the
+/// atmosphere. Below the horizon is a diffuse ground lit by the sky and the
+/// sun.
+pub struct Preetham {
+    sun_direction: Vec3,
+    theta_sun: f64,
+    turbidity: f64,
+    perez: [Perez; 3],
+    zenith: [f64; 3],
+    ground: Vec3,
+    /// Scale from the model's luminance in kcd/m^2 to scene radiance.
+    scale: f64,
+}
+
+impl Preetham {
+    /// Creates a sky lit by the sun in `sun_direction`. `turbidity` ranges
+    /// from 2 for a very clear sky to about 10 for a hazy one.
+    pub fn new(sun_direction: Vec3, turbidity: f64, ground_albedo: Vec3) -> Preetham {
+        let sun_direction = sun_direction.unit_vector();
+        let theta_sun = sun_direction.e[1].clamp(0., 1.).acos();
+        let t = turbidity;
+
+        let perez = [
+            Perez::new(t, [[0.1787, -1.4630], [-0.3554, 0.4275], [-0.0227, 5.3251],
+                           [0.1206, -2.5771], [-0.0670, 0.3703]]),
+            Perez::new(t, [[-0.0193, -0.2592], [-0.0665, 0.0008], [-0.0004, 0.2125],
+                           [-0.0641, -0.8989], [-0.0033, 0.0452]]),
+            Perez::new(t, [[-0.0167, -0.2608], [-0.0950, 0.0092], [-0.0079, 0.2102],
+                           [-0.0441, -1.6537], [-0.0109, 0.0529]]),
+        ];
+
+        let chi = (4. / 9. - t / 120.) * (PI - 2. * theta_sun);
+        let zenith = [
+            ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192).max(0.),
+            zenith_chromaticity(t, theta_sun, [[0.00166, -0.00375, 0.00209, 0.],
+                                               [-0.02903, 0.06377, -0.03202, 0.00394],
+                                               [0.11693, -0.21196, 0.06052, 0.25886]]),
+            zenith_chromaticity(t, theta_sun, [[0.00275, -0.00610, 0.00317, 0.],
+                                               [-0.04214, 0.08970, -0.04153, 0.00516],
+                                               [0.15346, -0.26756, 0.06670, 0.26688]]),
+        ];
+
+        let mut sky = Preetham {
+            sun_direction,
+            theta_sun,
+            turbidity,
+            perez,
+            zenith,
+            ground: Vec3::new(0., 0., 0.),
+            scale: SCALE,
+        };
+        sky.ground = ground_albedo * sky.irradiance() / PI;
+        sky
+    }
+
+    /// Scales the sky and the sun to their luminance in physical units, where
+    /// daylight is far brighter than with the default scale and is meant to
+    /// be rendered through a physical camera.
+    pub fn physical(self) -> Preetham {
+        let scale = 1000. / unit_luminance();
+        Preetham {
+            ground: self.ground * (scale / self.scale),
+            scale,
+            ..self
+        }
+    }
+
+    /// The sun matching the sky, reddened by the air mass it shines through.
+    pub fn sun(&self) -> Sun {
+        Sun::new(self.sun_direction, SUN_RADIUS, self.sun_radiance())
+    }
+
+    fn sky_radiance(&self, direction: &Vec3) -> Vec3 {
+        let theta = direction.e[1].clamp(0., 1.).acos();
+        let gamma = Vec3::dot(direction, &self.sun_direction).clamp(-1., 1.).acos();
+
+        let mut values = [0.; 3];
+        for (i, value) in values.iter_mut().enumerate() {
+            let perez = &self.perez[i];
+            *value = self.zenith[i] * perez.eval(theta, gamma) / perez.eval(0., self.theta_sun);
+        }
+        let [luminance, x, y] = values;
+        if y <= 0. {
+            return Vec3::new(0., 0., 0.);
+        }
+        let xyz = Vec3::new(x / y * luminance, luminance, (1. - x - y) / y * luminance);
+        xyz_to_rgb(&xyz) * self.scale
+    }
+
+    /// Radiance of the sun disk from the spectral transmittance of Rayleigh
+    /// and aerosol scattering along the path through the atmosphere.
+    fn sun_radiance(&self) -> Vec3 {
+        if self.sun_direction.e[1] <= 0. {
+            return Vec3::new(0., 0., 0.);
+        }
+        let degrees = self.theta_sun.to_degrees();
+        let air_mass = (self.theta_sun.cos() + 0.15 * (93.885 - degrees).powf(-1.253)).recip();
+        let beta = 0.04608 * self.turbidity - 0.04586;
+
+        let mut xyz = Vec3::new(0., 0., 0.);
+        let mut luminance = 0.;
+        let mut lambda = LAMBDA_MIN;
+        while lambda < LAMBDA_MAX {
+            let l = lambda + 0.5;
+            let um = l * 1e-3;
+            let rayleigh = 0.008735 * um.powf(-4.08);
+            let aerosol = beta * um.powf(-1.3);
+            let emitted = cie_xyz(l) * planck(l, SUN_TEMPERATURE);
+            xyz += emitted * (-air_mass * (rayleigh + aerosol)).exp();
+            luminance += emitted.e[1];
+            lambda += 1.;
+        }
+        xyz_to_rgb(&(xyz * (SUN_LUMINANCE / luminance))) * self.scale
+    }
+
+    /// Irradiance on a horizontal surface from the sky and the sun.
+    fn irradiance(&self) -> Vec3 {
+        const THETA_STEPS: usize = 16;
+        const PHI_STEPS: usize = 32;
+        let d_theta = 0.5 * PI / THETA_STEPS as f64;
+        let d_phi = 2. * PI / PHI_STEPS as f64;
+
+        let mut irradiance = Vec3::new(0., 0., 0.);
+        for i in 0..THETA_STEPS {
+            let theta = (i as f64 + 0.5) * d_theta;
+            for j in 0..PHI_STEPS {
+                let phi = (j as f64 + 0.5) * d_phi;
+                let direction = Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+                irradiance += self.sky_radiance(&direction) * (theta.cos() * theta.sin() * d_theta * d_phi);
+            }
+        }
+        let solid_angle = 2. * PI * (1. - SUN_RADIUS.cos());
+        irradiance + self.sun_radiance() * (solid_angle * self.sun_direction.e[1].max(0.))
+    }
+}
+
+impl Environment for Preetham {
+    fn radiance(&self, direction: &Vec3) -> Vec3 {
+        let direction = direction.unit_vector();
+        if direction.e[1] < 0. {
+            self.ground
+        } else {
+            self.sky_radiance(&direction)
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use ray::Ray;
+use vec3::Vec3;
+use hitable::{HitRecord, Hitable};
+
+/// A placement of an object: scaled uniformly, then rotated about an axis
+/// through the origin, then translated.
+#[derive(Copy, Clone)]
+pub struct Transform {
+    translation: Vec3,
+    axis: Vec3,
+    angle: f64,
+    scale: f64,
+}
+
+impl Transform {
+    pub fn identity() -> Transform {
+        Transform {
+            translation: Vec3::new(0., 0., 0.),
+            axis: Vec3::new(0., 1., 0.),
+            angle: 0.,
+            scale: 1.,
+        }
+    }
+
+    pub fn translate(self, translation: Vec3) -> Transform {
+        Transform { translation, ..self }
+    }
+
+    /// Rotates by `degrees` counterclockwise about `axis`.
+    pub fn rotate(self, axis: Vec3, degrees: f64) -> Transform {
+        Transform { axis: axis.unit_vector(), angle: degrees.to_radians(), ..self }
+    }
+
+    pub fn scale(self, scale: f64) -> Transform {
+        Transform { scale, ..self }
+    }
+
+    fn lerp(&self, other: &Transform, t: f64) -> Transform {
+        let axis = self.axis * (1. - t) + other.axis * t;
+        Transform {
+            translation: self.translation * (1. - t) + other.translation * t,
+            axis: if axis.length() > 0. { axis.unit_vector() } else { self.axis },
+            angle: self.angle * (1. - t) + other.angle * t,
+            scale: self.scale * (1. - t) + other.scale * t,
+        }
+    }
+
+    /// Rotates `v` by `angle` about the axis (Rodrigues' formula).
+    fn rotate_vector(&self, v: &Vec3, angle: f64) -> Vec3 {
+        let (sin, cos) = angle.sin_cos();
+        *v * cos + Vec3::cross(&self.axis, v) * sin + self.axis * (Vec3::dot(&self.axis, v) * (1. - cos))
+    }
+
+    /// Maps a ray in world space to the space of the object, keeping its
+    /// parameterization.
+    fn object_ray(&self, ray: &Ray) -> Ray {
+        let origin = self.rotate_vector(&(ray.origin - self.translation), -self.angle) / self.scale;
+        let direction = self.rotate_vector(&ray.direction, -self.angle) / self.scale;
+        ray.spawn(origin, direction)
+    }
+}
+
+/// An object whose transform is interpolated from `start` at `time0` to
+/// `end` at `time1`, so that it is blurred by the camera shutter.
+pub struct Animated<H> {
+    object: H,
+    start: Transform,
+    end: Transform,
+    time0: f64,
+    time1: f64,
+}
+
+impl<H> Animated<H> {
+    pub fn new(object: H, start: Transform, end: Transform, time0: f64, time1: f64) -> Animated<H> {
+        Animated {
+            object,
+            start,
+            end,
+            time0,
+            time1,
+        }
+    }
+
+    fn transform(&self, time: f64) -> Transform {
+        if self.time1 == self.time0 {
+            return self.start;
+        }
+        let t = ((time - self.time0) / (self.time1 - self.time0)).clamp(0., 1.);
+        self.start.lerp(&self.end, t)
+    }
+}
+
+impl<'a, H> Hitable<'a> for Animated<H>
+where
+    H: Hitable<'a>
+{
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'a>> {
+        let transform = self.transform(ray.time);
+        let mut hrec = self.object.hit(&transform.object_ray(ray), t_min, t_max)?;
+        hrec.p = ray.point_at_parameter(hrec.t);
+        hrec.normal = transform.rotate_vector(&hrec.normal, transform.angle);
+        Some(hrec)
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        let transform = self.transform(ray.time);
+        self.object.transmittance(&transform.object_ray(ray), t_min, t_max)
+    }
+}
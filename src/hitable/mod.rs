@@ -3,12 +3,16 @@ use vec3::Vec3;
 use material::Material;
 
 pub mod surfaces;
+pub mod volumes;
+pub mod animated;
 
 pub struct HitRecord<'a>
 {
     pub t: f64,
     pub p: Vec3,
     pub normal: Vec3,
+    pub u: f64,
+    pub v: f64,
     pub material: Box<Material + 'a>,
 }
 
@@ -19,14 +23,31 @@ impl<'a> HitRecord<'a>
             t,
             p,
             normal,
+            u: 0.,
+            v: 0.,
             material,
         }
     }
+
+    /// Sets the surface coordinates used to look up textures.
+    pub fn with_uv(self, u: f64, v: f64) -> HitRecord<'a> {
+        HitRecord {
+            u,
+            v,
+            ..self
+        }
+    }
 }
 
 pub trait Hitable<'a>
 {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'a>>;
+
+    /// The fraction of light that gets through between `t_min` and `t_max`,
+    /// as seen by shadow rays. Surfaces block all light.
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        if self.hit(ray, t_min, t_max).is_some() { 0. } else { 1. }
+    }
 }
 
 type BoxedHitable<'a> = Box<Hitable<'a> + Sync>;
@@ -55,4 +76,15 @@ impl<'a> Hitable<'a> for World<'a> {
         };
         self.list.iter().fold((None, t_max), get_closer_hit).0
     }
+
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        let mut transmittance = 1.;
+        for item in self.list.iter() {
+            transmittance *= item.transmittance(ray, t_min, t_max);
+            if transmittance == 0. {
+                break;
+            }
+        }
+        transmittance
+    }
 }
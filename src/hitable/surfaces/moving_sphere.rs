@@ -0,0 +1,70 @@
+use ray::Ray;
+use vec3::Vec3;
+use hitable::{HitRecord, Hitable};
+use hitable::surfaces::sphere::Sphere;
+use material::Material;
+
+/// A sphere moving in a straight line from `center0` at `time0` to
+/// `center1` at `time1`, resting at either end outside that interval.
+#[derive(Copy, Clone)]
+pub struct MovingSphere<T>
+where
+    T: Material + Copy + Clone
+{
+    center0: Vec3,
+    center1: Vec3,
+    time0: f64,
+    time1: f64,
+    radius: f64,
+    material: T,
+}
+
+impl<T> MovingSphere<T>
+where
+    T: Material + Copy + Clone
+{
+    pub fn new(center0: Vec3, center1: Vec3, time0: f64, time1: f64, radius: f64, material: T) -> MovingSphere<T> {
+        MovingSphere {
+            center0,
+            center1,
+            time0,
+            time1,
+            radius,
+            material,
+        }
+    }
+
+    pub fn center(&self, time: f64) -> Vec3 {
+        if self.time1 == self.time0 {
+            return self.center0;
+        }
+        let t = ((time - self.time0) / (self.time1 - self.time0)).clamp(0., 1.);
+        self.center0 + (self.center1 - self.center0) * t
+    }
+}
+
+impl<'a, T> Hitable<'a> for MovingSphere<T>
+where
+    T: Material + Copy + Clone + 'a
+{
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'a>> {
+        Sphere::new(self.center(ray.time), self.radius, self.material).hit(ray, t_min, t_max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use material::common::lambertian::Lambertian;
+
+    #[test]
+    fn rests_outside_the_time_interval() {
+        let material = Lambertian::new(Vec3::new(0.5, 0.5, 0.5));
+        let sphere = MovingSphere::new(Vec3::new(0., 0., 0.), Vec3::new(2., 0., 0.), 1., 2., 0.5, material);
+        assert_eq!(sphere.center(1.5).e, [1., 0., 0.]);
+        assert_eq!(sphere.center(0.).e, [0., 0., 0.]);
+        assert_eq!(sphere.center(-3.).e, [0., 0., 0.]);
+        assert_eq!(sphere.center(2.5).e, [2., 0., 0.]);
+        assert_eq!(sphere.center(10.).e, [2., 0., 0.]);
+    }
+}
@@ -1,3 +1,5 @@
+use std::f64::consts::PI;
+
 use ray::Ray;
 use vec3::Vec3;
 use hitable::{HitRecord, Hitable};
@@ -26,6 +28,12 @@ where
     }
 }
 
+fn sphere_uv(normal: &Vec3) -> (f64, f64) {
+    let phi = normal.e[2].atan2(normal.e[0]);
+    let theta = normal.e[1].clamp(-1., 1.).asin();
+    (1. - (phi + PI) / (2. * PI), (theta + PI / 2.) / PI)
+}
+
 impl<'a, T> Hitable<'a> for Sphere<T>
 where
     T: Material + Copy + Clone + 'a
@@ -47,7 +55,8 @@ where
         if temp < t_max && temp > t_min {
             let p = ray.point_at_parameter(temp);
             let normal = (p - self.center).unit_vector();
-            let hrec = HitRecord::new(temp, p, normal, Box::new(self.material));
+            let (u, v) = sphere_uv(&normal);
+            let hrec = HitRecord::new(temp, p, normal, Box::new(self.material)).with_uv(u, v);
             return Some(hrec);
         }
 
@@ -55,7 +64,8 @@ where
         if temp < t_max && temp > t_min {
             let p = ray.point_at_parameter(temp);
             let normal = (p - self.center).unit_vector();
-            let hrec = HitRecord::new(temp, p, normal, Box::new(self.material));
+            let (u, v) = sphere_uv(&normal);
+            let hrec = HitRecord::new(temp, p, normal, Box::new(self.material)).with_uv(u, v);
             return Some(hrec);
         }
 
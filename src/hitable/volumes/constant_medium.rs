@@ -0,0 +1,77 @@
+use ray::Ray;
+use vec3::Vec3;
+use hitable::{HitRecord, Hitable};
+use material::Material;
+use common::rand;
+
+/// A homogeneous participating medium, such as fog or smoke, filling a
+/// convex `boundary`. Rays passing through it are scattered at exponentially
+/// distributed distances, using `phase` as the material at the scattering
+/// point.
+pub struct ConstantMedium<H, T>
+where
+    T: Material + Copy + Clone
+{
+    boundary: H,
+    density: f64,
+    phase: T,
+}
+
+impl<H, T> ConstantMedium<H, T>
+where
+    T: Material + Copy + Clone
+{
+    pub fn new(boundary: H, density: f64, phase: T) -> ConstantMedium<H, T> {
+        ConstantMedium {
+            boundary,
+            density,
+            phase,
+        }
+    }
+}
+
+impl<'a, H, T> ConstantMedium<H, T>
+where
+    H: Hitable<'a>,
+    T: Material + Copy + Clone
+{
+    /// The interval of `ray` parameters inside the boundary.
+    fn interval(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        let enter = self.boundary.hit(ray, -f64::MAX, f64::MAX)?;
+        let exit = self.boundary.hit(ray, enter.t + 0.0001, f64::MAX)?;
+
+        let t_enter = enter.t.max(t_min).max(0.);
+        let t_exit = exit.t.min(t_max);
+        if t_enter >= t_exit {
+            return None;
+        }
+        Some((t_enter, t_exit))
+    }
+}
+
+impl<'a, H, T> Hitable<'a> for ConstantMedium<H, T>
+where
+    H: Hitable<'a>,
+    T: Material + Copy + Clone + 'a
+{
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'a>> {
+        let (t_enter, t_exit) = self.interval(ray, t_min, t_max)?;
+        let length = ray.direction.length();
+        let distance_inside = (t_exit - t_enter) * length;
+        let hit_distance = -(1. - rand()).ln() / self.density;
+        if hit_distance > distance_inside {
+            return None;
+        }
+
+        let t = t_enter + hit_distance / length;
+        let p = ray.point_at_parameter(t);
+        Some(HitRecord::new(t, p, Vec3::new(1., 0., 0.), Box::new(self.phase)))
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        match self.interval(ray, t_min, t_max) {
+            Some((t_enter, t_exit)) => (-self.density * (t_exit - t_enter) * ray.direction.length()).exp(),
+            None => 1.,
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use std::fs::File;
+use std::io::{self, Read};
+
+const BRICK: usize = 8;
+
+/// A scalar field sampled on a regular grid of voxels.
+pub trait Grid {
+    fn resolution(&self) -> (usize, usize, usize);
+
+    fn voxel(&self, i: usize, j: usize, k: usize) -> f64;
+
+    fn max_value(&self) -> f64;
+
+    /// Trilinearly interpolates the field at a point in `[0, 1]^3`.
+    fn sample(&self, x: f64, y: f64, z: f64) -> f64 {
+        let (nx, ny, nz) = self.resolution();
+        let locate = |x: f64, n: usize| {
+            let x = (x * n as f64 - 0.5).clamp(0., (n - 1) as f64);
+            let i = (x as usize).min(n.saturating_sub(2));
+            (i, (i + 1).min(n - 1), x - i as f64)
+        };
+        let (i0, i1, tx) = locate(x, nx);
+        let (j0, j1, ty) = locate(y, ny);
+        let (k0, k1, tz) = locate(z, nz);
+
+        let lerp = |a: f64, b: f64, t: f64| a * (1. - t) + b * t;
+        let plane = |k| {
+            lerp(lerp(self.voxel(i0, j0, k), self.voxel(i1, j0, k), tx),
+                 lerp(self.voxel(i0, j1, k), self.voxel(i1, j1, k), tx),
+                 ty)
+        };
+        lerp(plane(k0), plane(k1), tz)
+    }
+}
+
+/// A grid storing every voxel.
+pub struct DenseGrid {
+    nx: usize,
+    ny: usize,
+    nz: usize,
+    data: Vec<f32>,
+    max: f64,
+}
+
+impl DenseGrid {
+    /// Creates a non-empty grid from voxels ordered with `x` varying
+    /// fastest.
+    pub fn new(nx: usize, ny: usize, nz: usize, data: Vec<f32>) -> DenseGrid {
+        assert!(nx > 0 && ny > 0 && nz > 0, "empty grid");
+        assert_eq!(data.len(), nx * ny * nz);
+        let max = data.iter().cloned().fold(0., f32::max) as f64;
+        DenseGrid {
+            nx,
+            ny,
+            nz,
+            data,
+            max,
+        }
+    }
+
+    /// Reads a raw grid file: the resolution as three little endian `u32`
+    /// followed by every voxel as a little endian `f32`, with `x` varying
+    /// fastest and `z` slowest.
+    pub fn from_file(path: &str) -> io::Result<DenseGrid> {
+        let mut bytes = Vec::new();
+        File::open(path)?.read_to_end(&mut bytes)?;
+        DenseGrid::parse(&bytes)
+    }
+
+    /// Decodes the contents of a raw grid file, see `from_file`.
+    pub fn parse(bytes: &[u8]) -> io::Result<DenseGrid> {
+        let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+        if bytes.len() < 12 {
+            return Err(invalid("truncated grid header"));
+        }
+
+        let word = |i: usize| [bytes[4 * i], bytes[4 * i + 1], bytes[4 * i + 2], bytes[4 * i + 3]];
+        let nx = u32::from_le_bytes(word(0)) as usize;
+        let ny = u32::from_le_bytes(word(1)) as usize;
+        let nz = u32::from_le_bytes(word(2)) as usize;
+        if nx == 0 || ny == 0 || nz == 0 {
+            return Err(invalid("empty grid"));
+        }
+        let count = nx.checked_mul(ny).and_then(|n| n.checked_mul(nz));
+        let size = count.and_then(|n| n.checked_mul(4)).and_then(|n| n.checked_add(12));
+        let (count, size) = match (count, size) {
+            (Some(count), Some(size)) => (count, size),
+            _ => return Err(invalid("invalid grid resolution")),
+        };
+        if bytes.len() < size {
+            return Err(invalid("truncated grid data"));
+        }
+
+        let data = (0..count).map(|i| f32::from_le_bytes(word(3 + i))).collect();
+        Ok(DenseGrid::new(nx, ny, nz, data))
+    }
+}
+
+impl Grid for DenseGrid {
+    fn resolution(&self) -> (usize, usize, usize) {
+        (self.nx, self.ny, self.nz)
+    }
+
+    fn voxel(&self, i: usize, j: usize, k: usize) -> f64 {
+        self.data[(k * self.ny + j) * self.nx + i] as f64
+    }
+
+    fn max_value(&self) -> f64 {
+        self.max
+    }
+}
+
+/// A grid that only stores the bricks of 8^3 voxels that are not empty,
+/// for sparse data such as smoke plumes.
+pub struct SparseGrid {
+    resolution: (usize, usize, usize),
+    bricks: (usize, usize, usize),
+    data: Vec<Option<Box<[f32]>>>,
+    max: f64,
+}
+
+impl SparseGrid {
+    pub fn from_dense(dense: &DenseGrid) -> SparseGrid {
+        let (nx, ny, nz) = dense.resolution();
+        let bricks = (nx.div_ceil(BRICK), ny.div_ceil(BRICK), nz.div_ceil(BRICK));
+        let mut data = Vec::with_capacity(bricks.0 * bricks.1 * bricks.2);
+        for bk in 0..bricks.2 {
+            for bj in 0..bricks.1 {
+                for bi in 0..bricks.0 {
+                    let mut brick = vec![0_f32; BRICK * BRICK * BRICK];
+                    let mut empty = true;
+                    for k in 0..BRICK {
+                        for j in 0..BRICK {
+                            for i in 0..BRICK {
+                                let (x, y, z) = (bi * BRICK + i, bj * BRICK + j, bk * BRICK + k);
+                                if x < nx && y < ny && z < nz {
+                                    let value = dense.voxel(x, y, z) as f32;
+                                    brick[(k * BRICK + j) * BRICK + i] = value;
+                                    empty &= value == 0.;
+                                }
+                            }
+                        }
+                    }
+                    data.push(if empty { None } else { Some(brick.into_boxed_slice()) });
+                }
+            }
+        }
+        SparseGrid {
+            resolution: (nx, ny, nz),
+            bricks,
+            data,
+            max: dense.max_value(),
+        }
+    }
+
+    pub fn from_file(path: &str) -> io::Result<SparseGrid> {
+        Ok(SparseGrid::from_dense(&DenseGrid::from_file(path)?))
+    }
+}
+
+impl Grid for SparseGrid {
+    fn resolution(&self) -> (usize, usize, usize) {
+        self.resolution
+    }
+
+    fn voxel(&self, i: usize, j: usize, k: usize) -> f64 {
+        let brick = ((k / BRICK) * self.bricks.1 + j / BRICK) * self.bricks.0 + i / BRICK;
+        match self.data[brick] {
+            Some(ref values) => values[((k % BRICK) * BRICK + j % BRICK) * BRICK + i % BRICK] as f64,
+            None => 0.,
+        }
+    }
+
+    fn max_value(&self) -> f64 {
+        self.max
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(resolution: [u32; 3], data: &[f32]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for n in resolution.iter() {
+            bytes.extend_from_slice(&n.to_le_bytes());
+        }
+        for v in data {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn reads_dense_grid() {
+        let data: Vec<f32> = (0..12).map(|i| i as f32).collect();
+        let grid = DenseGrid::parse(&encode([2, 3, 2], &data)).unwrap();
+        assert_eq!(grid.resolution(), (2, 3, 2));
+        assert_eq!(grid.voxel(1, 2, 1), 11.);
+        assert_eq!(grid.max_value(), 11.);
+        assert_eq!(grid.sample(0., 0., 0.), 0.);
+        assert_eq!(grid.sample(1., 1., 1.), 11.);
+        assert_eq!(grid.sample(0.5, 0., 0.), 0.5);
+    }
+
+    #[test]
+    fn sparse_grid_matches_dense() {
+        let data: Vec<f32> = (0..10 * 9 * 3).map(|i| if i % 7 == 0 { i as f32 } else { 0. }).collect();
+        let dense = DenseGrid::new(10, 9, 3, data);
+        let sparse = SparseGrid::from_dense(&dense);
+        for k in 0..3 {
+            for j in 0..9 {
+                for i in 0..10 {
+                    assert_eq!(sparse.voxel(i, j, k), dense.voxel(i, j, k));
+                }
+            }
+        }
+        assert_eq!(sparse.max_value(), dense.max_value());
+    }
+
+    #[test]
+    fn rejects_malformed_grid() {
+        let malformed = [
+            vec![1, 0, 0, 0],
+            encode([2, 1, 1], &[1.]),
+            encode([0, 1, 1], &[]),
+            encode([1, 1, 0], &[]),
+            encode([u32::MAX, u32::MAX, u32::MAX], &[1.]),
+            encode([u32::MAX, u32::MAX, 2], &[1.]),
+        ];
+        for bytes in malformed.iter() {
+            let error = DenseGrid::parse(bytes).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::f64::consts::PI;
+
+use ray::Ray;
+use vec3::Vec3;
+use hitable::{HitRecord, Hitable};
+use hitable::volumes::grid::Grid;
+use material::Material;
+use common::{rand, random_unit_vector};
+use spectrum::{planck, blackbody_peak, cie_xyz, xyz_to_output, RgbSpectrum, LAMBDA_MIN, LAMBDA_MAX};
+
+const EMISSION_TABLE_SIZE: usize = 64;
+
+/// A heterogeneous participating medium whose density, and optionally
+/// temperature, are given by voxel grids spanning the box from `min` to
+/// `max`. Scattering points are found with delta tracking and shadow rays
+/// are attenuated with ratio tracking.
+pub struct GridMedium<G>
+where
+    G: Grid
+{
+    density: G,
+    temperature: Option<G>,
+    min: Vec3,
+    max: Vec3,
+    density_scale: f64,
+    albedo: Vec3,
+    emission_norm: f64,
+    emission_table: Vec<Vec3>,
+}
+
+/// The event at a collision inside a grid medium: light is either scattered
+/// isotropically or absorbed, in which case the medium emits.
+#[derive(Copy, Clone)]
+struct Collision {
+    albedo: Vec3,
+    temperature: f64,
+    emission_norm: f64,
+    emission: Vec3,
+}
+
+impl Material for Collision {
+    fn scatter(&self, r_in: &Ray, hrec: &HitRecord) -> Option<(Vec3, Ray)> {
+        Some((self.albedo, r_in.spawn(hrec.p, random_unit_vector())))
+    }
+
+    fn eval(&self, _r_in: &Ray, _hrec: &HitRecord, _direction: &Vec3) -> Option<Vec3> {
+        Some(self.albedo / (4. * PI))
+    }
+
+    fn emitted(&self, _r_in: &Ray, _hrec: &HitRecord) -> Vec3 {
+        (Vec3::new(1., 1., 1.) - self.albedo) * self.emission
+    }
+
+    fn emitted_spectral(&self, _r_in: &Ray, _hrec: &HitRecord, wavelength: f64) -> f64 {
+        if self.temperature <= 0. {
+            return 0.;
+        }
+        let absorption = RgbSpectrum::reflectance(&(Vec3::new(1., 1., 1.) - self.albedo));
+        absorption.eval(wavelength) * self.emission_norm * planck(wavelength, self.temperature)
+    }
+}
+
+impl<G> GridMedium<G>
+where
+    G: Grid
+{
+    /// Creates a medium whose extinction coefficient is the grid value times
+    /// `density_scale`, scattering a fraction `albedo` of the light.
+    pub fn new(density: G, min: Vec3, max: Vec3, density_scale: f64, albedo: Vec3) -> GridMedium<G> {
+        GridMedium {
+            density,
+            temperature: None,
+            min,
+            max,
+            density_scale,
+            albedo,
+            emission_norm: 0.,
+            emission_table: Vec::new(),
+        }
+    }
+
+    /// Makes absorbed light be re-emitted as black body radiation with the
+    /// temperature in Kelvin given by `temperature`. The hottest voxel emits
+    /// with a peak radiance of `emission_scale`.
+    pub fn with_temperature(self, temperature: G, emission_scale: f64) -> GridMedium<G> {
+        let max_temperature = temperature.max_value();
+        let emission_norm = if max_temperature > 0. {
+            emission_scale / planck(blackbody_peak(max_temperature), max_temperature)
+        } else {
+            0.
+        };
+
+        let emission_table = (0..EMISSION_TABLE_SIZE).map(|i| {
+            let t = max_temperature * i as f64 / (EMISSION_TABLE_SIZE - 1) as f64;
+            if t <= 0. {
+                return Vec3::new(0., 0., 0.);
+            }
+            let mut xyz = Vec3::new(0., 0., 0.);
+            let mut lambda = LAMBDA_MIN;
+            while lambda < LAMBDA_MAX {
+                xyz += cie_xyz(lambda + 0.5) * (emission_norm * planck(lambda + 0.5, t));
+                lambda += 1.;
+            }
+            xyz_to_output(&xyz)
+        }).collect();
+
+        GridMedium {
+            temperature: Some(temperature),
+            emission_norm,
+            emission_table,
+            ..self
+        }
+    }
+
+    fn local(&self, p: &Vec3) -> Vec3 {
+        (*p - self.min) / (self.max - self.min)
+    }
+
+    fn extinction(&self, local: &Vec3) -> f64 {
+        self.density.sample(local.e[0], local.e[1], local.e[2]) * self.density_scale
+    }
+
+    fn emission(&self, temperature: f64) -> Vec3 {
+        let max_temperature = self.temperature.as_ref().map_or(0., |t| t.max_value());
+        if temperature <= 0. || max_temperature <= 0. {
+            return Vec3::new(0., 0., 0.);
+        }
+        let x = (temperature / max_temperature).min(1.) * (EMISSION_TABLE_SIZE - 1) as f64;
+        let i = (x as usize).min(EMISSION_TABLE_SIZE - 2);
+        let t = x - i as f64;
+        self.emission_table[i] * (1. - t) + self.emission_table[i + 1] * t
+    }
+
+    /// The interval of `ray` parameters inside the bounding box.
+    fn interval(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        let mut t0 = t_min;
+        let mut t1 = t_max;
+        for a in 0..3 {
+            let inv = ray.direction.e[a].recip();
+            let mut near = (self.min.e[a] - ray.origin.e[a]) * inv;
+            let mut far = (self.max.e[a] - ray.origin.e[a]) * inv;
+            if inv < 0. {
+                std::mem::swap(&mut near, &mut far);
+            }
+            t0 = t0.max(near);
+            t1 = t1.min(far);
+            if t1 <= t0 {
+                return None;
+            }
+        }
+        Some((t0, t1))
+    }
+
+    fn majorant(&self) -> f64 {
+        self.density.max_value() * self.density_scale
+    }
+}
+
+impl<'a, G> Hitable<'a> for GridMedium<G>
+where
+    G: Grid
+{
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'a>> {
+        let (t_enter, t_exit) = self.interval(ray, t_min, t_max)?;
+        let majorant = self.majorant();
+        if majorant <= 0. {
+            return None;
+        }
+
+        let step = (majorant * ray.direction.length()).recip();
+        let mut t = t_enter;
+        loop {
+            t -= (1. - rand()).ln() * step;
+            if t >= t_exit {
+                return None;
+            }
+            let p = ray.point_at_parameter(t);
+            let local = self.local(&p);
+            if rand() * majorant < self.extinction(&local) {
+                let temperature = self.temperature.as_ref()
+                                      .map_or(0., |g| g.sample(local.e[0], local.e[1], local.e[2]));
+                let collision = Collision {
+                    albedo: self.albedo,
+                    temperature,
+                    emission_norm: self.emission_norm,
+                    emission: self.emission(temperature),
+                };
+                return Some(HitRecord::new(t, p, Vec3::new(1., 0., 0.), Box::new(collision)));
+            }
+        }
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        let (t_enter, t_exit) = match self.interval(ray, t_min, t_max) {
+            Some(interval) => interval,
+            None => return 1.,
+        };
+        let majorant = self.majorant();
+        if majorant <= 0. {
+            return 1.;
+        }
+
+        let step = (majorant * ray.direction.length()).recip();
+        let mut transmittance = 1.;
+        let mut t = t_enter;
+        loop {
+            t -= (1. - rand()).ln() * step;
+            if t >= t_exit {
+                return transmittance;
+            }
+            let local = self.local(&ray.point_at_parameter(t));
+            transmittance *= 1. - self.extinction(&local) / majorant;
+        }
+    }
+}
@@ -0,0 +1,3 @@
+pub mod constant_medium;
+pub mod grid;
+pub mod grid_medium;
@@ -9,3 +9,13 @@ pub mod camera;
 pub mod material;
 
 pub mod common;
+
+pub mod spectrum;
+
+pub mod texture;
+
+pub mod light;
+
+pub mod atmosphere;
+
+pub mod environment;
@@ -0,0 +1,296 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::f64::consts::PI;
+
+use vec3::Vec3;
+use common::{orthonormal_basis, luminance};
+use light::{Light, LightSample, LightBounds};
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// The luminous intensity distribution of a fixture, read from an IES LM-63
+/// photometric file with type C photometry. Vertical angles are measured in
+/// degrees from the nadir and horizontal angles around it.
+pub struct IesProfile {
+    vertical: Vec<f64>,
+    horizontal: Vec<f64>,
+    /// Candela for each horizontal angle, at every vertical angle.
+    candela: Vec<Vec<f64>>,
+}
+
+impl IesProfile {
+    pub fn from_file(path: &str) -> io::Result<IesProfile> {
+        let mut text = String::new();
+        File::open(path)?.read_to_string(&mut text)?;
+        IesProfile::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> io::Result<IesProfile> {
+        let tilt = text.find("TILT=").ok_or_else(|| invalid_data("missing TILT line"))?;
+        let rest = &text[tilt..];
+        let (tilt_line, data) = rest.split_at(rest.find('\n').unwrap_or(rest.len()));
+        let values = data.split(|c: char| c == ',' || c.is_whitespace())
+                         .filter(|s| !s.is_empty())
+                         .map(|s| match s.parse::<f64>() {
+                             Ok(x) if x.is_finite() => Ok(x),
+                             _ => Err(invalid_data("invalid IES number")),
+                         })
+                         .collect::<io::Result<Vec<_>>>()?;
+        // Counts larger than the number of values cannot be valid, which also
+        // keeps them from overflowing.
+        let remaining = values.len();
+        let mut values = values.iter().cloned();
+        let mut next = || values.next().ok_or_else(|| invalid_data("truncated IES data"));
+        let count = |x: f64| {
+            if x >= 0. && x.fract() == 0. && x <= remaining as f64 {
+                Ok(x as usize)
+            } else {
+                Err(invalid_data("invalid IES count"))
+            }
+        };
+
+        if tilt_line.trim() == "TILT=INCLUDE" {
+            next()?;
+            let pairs = count(next()?)?;
+            for _ in 0..2 * pairs {
+                next()?;
+            }
+        } else if tilt_line.trim() != "TILT=NONE" {
+            return Err(invalid_data("unsupported IES tilt file"));
+        }
+
+        let _lamps = next()?;
+        let _lumens = next()?;
+        let multiplier = next()?;
+        let vertical_count = count(next()?)?;
+        let horizontal_count = count(next()?)?;
+        let photometric_type = next()?;
+        for _ in 0..4 {
+            next()?;
+        }
+        let ballast = next()?;
+        let _ballast_lamp = next()?;
+        let _watts = next()?;
+        if photometric_type != 1. {
+            return Err(invalid_data("only type C photometry is supported"));
+        }
+        if vertical_count == 0 || horizontal_count == 0 {
+            return Err(invalid_data("empty IES profile"));
+        }
+
+        let vertical = (0..vertical_count).map(|_| next()).collect::<io::Result<Vec<_>>>()?;
+        let horizontal = (0..horizontal_count).map(|_| next()).collect::<io::Result<Vec<_>>>()?;
+        let sorted = |angles: &[f64]| angles.windows(2).all(|pair| pair[0] <= pair[1]);
+        if !sorted(&vertical) || !sorted(&horizontal) {
+            return Err(invalid_data("IES angles out of order"));
+        }
+        let mut candela = Vec::with_capacity(horizontal_count);
+        for _ in 0..horizontal_count {
+            let row = (0..vertical_count).map(|_| next().map(|c| c * multiplier * ballast))
+                                         .collect::<io::Result<Vec<_>>>()?;
+            candela.push(row);
+        }
+
+        Ok(IesProfile {
+            vertical,
+            horizontal,
+            candela,
+        })
+    }
+
+    /// Intensity in candela at `vertical` degrees from the nadir and
+    /// `horizontal` degrees around it, using the symmetry of the profile
+    /// implied by its first and last horizontal angles. The fixture emits
+    /// nothing outside the measured vertical angles.
+    pub fn intensity(&self, vertical: f64, horizontal: f64) -> f64 {
+        if vertical < self.vertical[0] || vertical > *self.vertical.last().unwrap() {
+            return 0.;
+        }
+        let first = self.horizontal[0];
+        let last = *self.horizontal.last().unwrap();
+        let h = horizontal.rem_euclid(360.);
+        let h = if first == 90. && last == 270. {
+            // Symmetric about the 90-270 degree plane.
+            if (90. ..=270.).contains(&h) { h } else { (180. - h).rem_euclid(360.) }
+        } else if last == 0. {
+            0.
+        } else if last == 90. {
+            let h = h % 180.;
+            if h > 90. { 180. - h } else { h }
+        } else if last == 180. {
+            if h > 180. { 360. - h } else { h }
+        } else {
+            h
+        };
+
+        let (i0, i1, ti) = locate(&self.horizontal, h);
+        let (j0, j1, tj) = locate(&self.vertical, vertical);
+        let at = |i: usize| self.candela[i][j0] * (1. - tj) + self.candela[i][j1] * tj;
+        at(i0) * (1. - ti) + at(i1) * ti
+    }
+}
+
+/// Finds the segment of the sorted `angles` containing `x` and the position
+/// of `x` within it, clamping at the ends.
+fn locate(angles: &[f64], x: f64) -> (usize, usize, f64) {
+    let n = angles.len();
+    if n == 1 || x <= angles[0] {
+        return (0, 0, 0.);
+    }
+    if x >= angles[n - 1] {
+        return (n - 1, n - 1, 0.);
+    }
+    let i = angles.partition_point(|&a| a <= x) - 1;
+    let width = angles[i + 1] - angles[i];
+    (i, i + 1, if width > 0. { (x - angles[i]) / width } else { 0. })
+}
+
+/// A point light whose intensity in each direction follows a measured
+/// photometric profile.
+pub struct IesLight {
+    position: Vec3,
+    nadir: Vec3,
+    profile: IesProfile,
+    colour: Vec3,
+}
+
+impl IesLight {
+    /// A fixture hanging at `position` pointing straight down.
+    pub fn point(position: Vec3, profile: IesProfile) -> IesLight {
+        IesLight::spot(position, Vec3::new(0., -1., 0.), profile)
+    }
+
+    /// A fixture at `position` whose nadir is aimed along `direction`.
+    pub fn spot(position: Vec3, direction: Vec3, profile: IesProfile) -> IesLight {
+        IesLight {
+            position,
+            nadir: direction.unit_vector(),
+            profile,
+            colour: Vec3::new(1., 1., 1.),
+        }
+    }
+
+    /// Multiplies the intensity in candela, for instance to convert it into
+    /// scene units or to tint the light.
+    pub fn colour(self, colour: Vec3) -> IesLight {
+        IesLight { colour, ..self }
+    }
+}
+
+impl Light for IesLight {
+    fn sample(&self, p: &Vec3) -> Option<LightSample> {
+        let to_light = self.position - *p;
+        let distance = to_light.length();
+        if distance <= 0. {
+            return None;
+        }
+        let direction = to_light / distance;
+
+        let emitted = -direction;
+        let (s, t) = orthonormal_basis(&self.nadir);
+        let vertical = Vec3::dot(&emitted, &self.nadir).clamp(-1., 1.).acos().to_degrees();
+        let horizontal = Vec3::dot(&emitted, &t).atan2(Vec3::dot(&emitted, &s)).to_degrees();
+        let intensity = self.profile.intensity(vertical, horizontal);
+        if intensity <= 0. {
+            return None;
+        }
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: self.colour * (intensity / (distance * distance)),
+            spectrum: None,
+        })
+    }
+
+    fn bounds(&self) -> Option<LightBounds> {
+        let peak = self.profile.candela.iter().flat_map(|row| row.iter()).cloned().fold(0., f64::max);
+        let power = 4. * PI * peak * luminance(&self.colour);
+        Some(LightBounds::omnidirectional(self.position, self.position, power))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILE: &str = "IESNA:LM-63-2002\n[TEST] test\nTILT=NONE\n\
+                           1 1000 2 3 2 1 1 0 0 0\n\
+                           1 1 100\n\
+                           0 45 90\n\
+                           0 180\n\
+                           100 50 0\n\
+                           200 100 0\n";
+
+    #[test]
+    fn reads_ies_profile() {
+        let profile = IesProfile::parse(PROFILE).unwrap();
+        assert_eq!(profile.intensity(0., 0.), 200.);
+        assert_eq!(profile.intensity(45., 0.), 100.);
+        assert_eq!(profile.intensity(22.5, 0.), 150.);
+        assert_eq!(profile.intensity(90., 0.), 0.);
+        assert_eq!(profile.intensity(0., 180.), 400.);
+        assert_eq!(profile.intensity(0., 90.), 300.);
+        assert_eq!(profile.intensity(0., 270.), 300.);
+        assert_eq!(profile.intensity(120., 0.), 0.);
+    }
+
+    #[test]
+    fn mirrors_about_the_90_270_plane() {
+        let text = PROFILE.replace("2 3 2 1", "2 3 3 1")
+                          .replace("0 180\n", "90 180 270\n")
+                          .replace("200 100 0\n", "200 100 0\n300 150 0\n");
+        let profile = IesProfile::parse(&text).unwrap();
+        assert_eq!(profile.intensity(0., 90.), 200.);
+        assert_eq!(profile.intensity(0., 180.), 400.);
+        assert_eq!(profile.intensity(0., 0.), profile.intensity(0., 180.));
+        assert_eq!(profile.intensity(0., 45.), profile.intensity(0., 135.));
+        assert_eq!(profile.intensity(45., 300.), profile.intensity(45., 240.));
+        assert_eq!(profile.intensity(0., -90.), 600.);
+    }
+
+    #[test]
+    fn emits_nothing_outside_vertical_angles() {
+        let downlight = PROFILE.replace("100 50 0\n200 100 0\n", "100 50 20\n200 100 40\n");
+        let profile = IesProfile::parse(&downlight).unwrap();
+        assert_eq!(profile.intensity(90., 0.), 40.);
+        assert_eq!(profile.intensity(90.5, 0.), 0.);
+        assert_eq!(profile.intensity(180., 180.), 0.);
+
+        let uplight = downlight.replace("0 45 90", "90 135 180");
+        let profile = IesProfile::parse(&uplight).unwrap();
+        assert_eq!(profile.intensity(90., 0.), 200.);
+        assert_eq!(profile.intensity(45., 0.), 0.);
+        assert_eq!(profile.intensity(0., 0.), 0.);
+    }
+
+    #[test]
+    fn skips_included_tilt() {
+        let text = PROFILE.replace("TILT=NONE\n", "TILT=INCLUDE\n1 2 0 90 1 1\n");
+        let profile = IesProfile::parse(&text).unwrap();
+        assert_eq!(profile.intensity(0., 0.), 200.);
+    }
+
+    #[test]
+    fn rejects_malformed_profile() {
+        let malformed = [
+            PROFILE.replace("TILT=NONE", "TILT=lamp.tlt"),
+            PROFILE.replace("TILT=NONE", ""),
+            PROFILE.replace("200 100 0\n", "200 100\n"),
+            PROFILE.replace("200 100 0", "200 x 0"),
+            PROFILE.replace("200 100 0", "200 nan 0"),
+            PROFILE.replace("2 3 2 1", "2 3 2 2"),
+            PROFILE.replace("2 3 2 1", "2 0 2 1"),
+            PROFILE.replace("2 3 2 1", "2 -3 2 1"),
+            PROFILE.replace("2 3 2 1", "2 1e30 2 1"),
+            PROFILE.replace("2 3 2 1", "2 3.5 2 1"),
+            PROFILE.replace("0 45 90", "0 90 45"),
+            PROFILE.replace("TILT=NONE\n", "TILT=INCLUDE\n1 1e300\n"),
+        ];
+        for text in malformed.iter() {
+            let error = IesProfile::parse(text).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
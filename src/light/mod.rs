@@ -0,0 +1,470 @@
+use std::f64::consts::PI;
+
+use vec3::Vec3;
+use common::{rand, to_world, luminance};
+use hitable::surfaces::sphere::Sphere;
+use material::common::emissive::Emissive;
+use spectrum::{Spectrum, Intensity};
+
+pub mod ies;
+pub mod tree;
+
+/// Light arriving at a point from a sampled light source.
+pub struct LightSample {
+    /// Unit vector pointing towards the light.
+    pub direction: Vec3,
+    /// Distance to the light along `direction`, for shadow rays.
+    pub distance: f64,
+    /// Incident radiance divided by the probability of the sample.
+    pub radiance: Vec3,
+    /// The emission spectrum of lights defined by one, with the factor that
+    /// turns it into the incident radiance of the sample, for spectral
+    /// rendering to evaluate at the wavelengths of a path.
+    pub spectrum: Option<(Spectrum, f64)>,
+}
+
+/// Bounds on the position, direction and power of the emission of one or
+/// more lights, used to estimate their contribution at a point.
+#[derive(Copy, Clone)]
+pub struct LightBounds {
+    pub min: Vec3,
+    pub max: Vec3,
+    pub power: f64,
+    /// Axis of the cone bounding the directions the lights face.
+    pub axis: Vec3,
+    /// Half angle of the cone bounding the directions the lights face.
+    pub theta_o: f64,
+    /// Angle beyond `theta_o` over which the lights still emit.
+    pub theta_e: f64,
+}
+
+impl LightBounds {
+    /// Bounds of a light emitting `power` in all directions from the box
+    /// from `min` to `max`.
+    pub fn omnidirectional(min: Vec3, max: Vec3, power: f64) -> LightBounds {
+        LightBounds {
+            min,
+            max,
+            power,
+            axis: Vec3::new(0., 0., 1.),
+            theta_o: PI,
+            theta_e: PI / 2.,
+        }
+    }
+
+    pub fn union(&self, other: &LightBounds) -> LightBounds {
+        let min = Vec3::new(self.min.e[0].min(other.min.e[0]),
+                            self.min.e[1].min(other.min.e[1]),
+                            self.min.e[2].min(other.min.e[2]));
+        let max = Vec3::new(self.max.e[0].max(other.max.e[0]),
+                            self.max.e[1].max(other.max.e[1]),
+                            self.max.e[2].max(other.max.e[2]));
+        let (axis, theta_o) = union_cone(self.axis, self.theta_o, other.axis, other.theta_o);
+        LightBounds {
+            min,
+            max,
+            power: self.power + other.power,
+            axis,
+            theta_o,
+            theta_e: self.theta_e.max(other.theta_e),
+        }
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// An estimate of the light arriving at `p` from within the bounds
+    /// (Conty Estevez and Kulla 2018).
+    pub fn importance(&self, p: &Vec3) -> f64 {
+        if self.power <= 0. {
+            return 0.;
+        }
+        let radius = (self.max - self.min).length() * 0.5;
+        let to_point = *p - self.centroid();
+        let distance2 = to_point.squared_length().max(radius * radius);
+        if distance2 <= 0. {
+            // A point light seen from its own position.
+            return 0.;
+        }
+        let distance = to_point.length();
+
+        let theta_w = if distance > 0. {
+            (Vec3::dot(&self.axis, &to_point) / distance).clamp(-1., 1.).acos()
+        } else {
+            0.
+        };
+        let theta_b = if distance > radius { (radius / distance).asin() } else { PI };
+        let theta = (theta_w - self.theta_o - theta_b).max(0.);
+        // A hard edged cone has `theta_e` zero and still lights its inside.
+        if theta > self.theta_e {
+            return 0.;
+        }
+        self.power * theta.cos() / distance2
+    }
+}
+
+/// The smallest cone containing the cones around `a` and `b` with half
+/// angles `theta_a` and `theta_b`.
+fn union_cone(a: Vec3, theta_a: f64, b: Vec3, theta_b: f64) -> (Vec3, f64) {
+    let theta_d = Vec3::dot(&a, &b).clamp(-1., 1.).acos();
+    if (theta_d + theta_b).min(PI) <= theta_a {
+        return (a, theta_a);
+    }
+    if (theta_d + theta_a).min(PI) <= theta_b {
+        return (b, theta_b);
+    }
+    let theta_o = (theta_a + theta_d + theta_b) / 2.;
+    if theta_o >= PI {
+        return (a, PI);
+    }
+    let w = Vec3::cross(&a, &b);
+    if w.length() <= 0. {
+        return (a, PI);
+    }
+    // Rotates `a` towards `b` about `w` (Rodrigues' formula).
+    let w = w.unit_vector();
+    let angle = theta_o - theta_a;
+    let axis = a * angle.cos() + Vec3::cross(&w, &a) * angle.sin() + w * (Vec3::dot(&w, &a) * (1. - angle.cos()));
+    (axis.unit_vector(), theta_o)
+}
+
+/// A light source that the integrator samples directly, with shadow rays.
+pub trait Light {
+    fn sample(&self, p: &Vec3) -> Option<LightSample>;
+
+    /// Bounds used to importance sample the light among many others, or
+    /// `None` for lights at infinity, which are always sampled.
+    fn bounds(&self) -> Option<LightBounds> {
+        None
+    }
+
+    /// Radiance seen along a ray leaving the scene in `direction`, for lights
+    /// at infinity that cover a part of the sky. The integrator only adds it
+    /// when the light was not sampled directly at the previous vertex.
+    fn emitted(&self, _direction: &Vec3) -> Vec3 {
+        Vec3::new(0., 0., 0.)
+    }
+}
+
+/// A light infinitely far away, such as the sun, arriving from `direction`.
+pub struct DirectionalLight {
+    direction: Vec3,
+    radiance: Vec3,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vec3, radiance: Vec3) -> DirectionalLight {
+        DirectionalLight {
+            direction: direction.unit_vector(),
+            radiance,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _p: &Vec3) -> Option<LightSample> {
+        Some(LightSample {
+            direction: self.direction,
+            distance: f64::MAX,
+            radiance: self.radiance,
+            spectrum: None,
+        })
+    }
+}
+
+/// A light emitting equally in all directions from a single point.
+pub struct PointLight {
+    position: Vec3,
+    intensity: Vec3,
+    spectrum: Option<Spectrum>,
+}
+
+impl PointLight {
+    /// Creates a light with radiant `intensity` per steradian.
+    pub fn new(position: Vec3, intensity: Vec3) -> PointLight {
+        PointLight {
+            position,
+            intensity,
+            spectrum: None,
+        }
+    }
+
+    /// A light with the spectrum of a black body at `temperature` Kelvin.
+    pub fn blackbody(position: Vec3, temperature: f64, intensity: Intensity) -> PointLight {
+        let spectrum = intensity.blackbody(temperature, 4. * PI);
+        PointLight {
+            position,
+            intensity: spectrum.to_rgb(),
+            spectrum: Some(spectrum),
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, p: &Vec3) -> Option<LightSample> {
+        let to_light = self.position - *p;
+        let distance = to_light.length();
+        if distance <= 0. {
+            return None;
+        }
+        let falloff = 1. / (distance * distance);
+        Some(LightSample {
+            direction: to_light / distance,
+            distance,
+            radiance: self.intensity * falloff,
+            spectrum: self.spectrum.map(|spectrum| (spectrum, falloff)),
+        })
+    }
+
+    fn bounds(&self) -> Option<LightBounds> {
+        let power = 4. * PI * luminance(&self.intensity);
+        Some(LightBounds::omnidirectional(self.position, self.position, power))
+    }
+}
+
+/// A point light restricted to a cone, fading out smoothly between an inner
+/// and an outer cone angle.
+pub struct SpotLight {
+    position: Vec3,
+    direction: Vec3,
+    intensity: Vec3,
+    cos_inner: f64,
+    cos_outer: f64,
+}
+
+impl SpotLight {
+    /// Creates a light at `position` pointing along `direction`, at full
+    /// `intensity` within `inner` degrees of its axis and dark beyond
+    /// `outer` degrees.
+    pub fn new(position: Vec3, direction: Vec3, intensity: Vec3, inner: f64, outer: f64) -> SpotLight {
+        let outer = outer.max(inner);
+        SpotLight {
+            position,
+            direction: direction.unit_vector(),
+            intensity,
+            cos_inner: inner.to_radians().cos(),
+            cos_outer: outer.to_radians().cos(),
+        }
+    }
+
+    fn falloff(&self, cosine: f64) -> f64 {
+        if cosine >= self.cos_inner {
+            return 1.;
+        }
+        let x = ((cosine - self.cos_outer) / (self.cos_inner - self.cos_outer)).clamp(0., 1.);
+        x * x * (3. - 2. * x)
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, p: &Vec3) -> Option<LightSample> {
+        let to_light = self.position - *p;
+        let distance = to_light.length();
+        if distance <= 0. {
+            return None;
+        }
+        let direction = to_light / distance;
+        let falloff = self.falloff(-Vec3::dot(&direction, &self.direction));
+        if falloff <= 0. {
+            return None;
+        }
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: self.intensity * (falloff / (distance * distance)),
+            spectrum: None,
+        })
+    }
+
+    fn bounds(&self) -> Option<LightBounds> {
+        let power = 2. * PI * luminance(&self.intensity)
+                    * ((1. - self.cos_inner) + (self.cos_inner - self.cos_outer) / 2.);
+        Some(LightBounds {
+            min: self.position,
+            max: self.position,
+            power,
+            axis: self.direction,
+            theta_o: self.cos_inner.clamp(-1., 1.).acos(),
+            theta_e: self.cos_outer.clamp(-1., 1.).acos() - self.cos_inner.clamp(-1., 1.).acos(),
+        })
+    }
+}
+
+/// A disk light at infinity covering a cone of directions, such as the sun
+/// seen from the ground, which casts soft shadows.
+pub struct Sun {
+    direction: Vec3,
+    cos_radius: f64,
+    radiance: Vec3,
+}
+
+impl Sun {
+    /// Creates a disk centred on `direction` with the angular radius
+    /// `radius` in radians.
+    pub fn new(direction: Vec3, radius: f64, radiance: Vec3) -> Sun {
+        Sun {
+            direction: direction.unit_vector(),
+            cos_radius: radius.cos(),
+            radiance,
+        }
+    }
+
+    fn solid_angle(&self) -> f64 {
+        2. * PI * (1. - self.cos_radius)
+    }
+}
+
+impl Light for Sun {
+    fn sample(&self, _p: &Vec3) -> Option<LightSample> {
+        let cos_theta = 1. - rand() * (1. - self.cos_radius);
+        let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+        let phi = 2. * PI * rand();
+        let local = Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+        Some(LightSample {
+            direction: to_world(&local, &self.direction),
+            distance: f64::MAX,
+            radiance: self.radiance * self.solid_angle(),
+            spectrum: None,
+        })
+    }
+
+    fn emitted(&self, direction: &Vec3) -> Vec3 {
+        if Vec3::dot(&direction.unit_vector(), &self.direction) >= self.cos_radius {
+            self.radiance
+        } else {
+            Vec3::new(0., 0., 0.)
+        }
+    }
+}
+
+/// A sphere emitting `radiance` uniformly from its surface, sampled over the
+/// cone of directions it subtends.
+pub struct SphereLight {
+    center: Vec3,
+    radius: f64,
+    emissive: Emissive,
+    radiance: Vec3,
+    spectrum: Option<Spectrum>,
+}
+
+impl SphereLight {
+    pub fn new(center: Vec3, radius: f64, radiance: Vec3) -> SphereLight {
+        SphereLight {
+            center,
+            radius,
+            emissive: Emissive::rgb(radiance),
+            radiance,
+            spectrum: None,
+        }
+    }
+
+    /// A sphere with the spectrum of a black body at `temperature` Kelvin.
+    /// Watts and lumens give the power emitted by the whole sphere.
+    pub fn blackbody(center: Vec3, radius: f64, temperature: f64, intensity: Intensity) -> SphereLight {
+        let area = 4. * PI * radius * radius;
+        let spectrum = intensity.blackbody(temperature, PI * area);
+        let emissive = Emissive::new(spectrum);
+        SphereLight {
+            center,
+            radius,
+            emissive,
+            radiance: emissive.rgb_radiance(),
+            spectrum: Some(spectrum),
+        }
+    }
+
+    /// The geometry of the light, to be added to the world so that it is
+    /// seen by the camera and by paths that did not sample it.
+    pub fn sphere(&self) -> Sphere<Emissive> {
+        Sphere::new(self.center, self.radius, self.emissive.as_light())
+    }
+}
+
+impl Light for SphereLight {
+    fn sample(&self, p: &Vec3) -> Option<LightSample> {
+        let to_center = self.center - *p;
+        let distance2 = to_center.squared_length();
+        let radius2 = self.radius * self.radius;
+        if distance2 <= radius2 {
+            return None;
+        }
+        let distance = distance2.sqrt();
+        let sin2_max = radius2 / distance2;
+        let cos_max = (1. - sin2_max).max(0.).sqrt();
+        let one_minus_cos_max = sin2_max / (1. + cos_max);
+
+        let cos_theta = 1. - rand() * one_minus_cos_max;
+        let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+        let phi = 2. * PI * rand();
+        let local = Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+        let direction = to_world(&local, &(to_center / distance));
+
+        // Stops shadow rays just short of the sphere itself.
+        let along = distance * cos_theta;
+        let hit = along - (radius2 - distance2 * sin_theta * sin_theta).max(0.).sqrt();
+        let solid_angle = 2. * PI * one_minus_cos_max;
+        Some(LightSample {
+            direction,
+            distance: hit * (1. - 1e-4),
+            radiance: self.radiance * solid_angle,
+            spectrum: self.spectrum.map(|spectrum| (spectrum, solid_angle)),
+        })
+    }
+
+    fn bounds(&self) -> Option<LightBounds> {
+        let extent = Vec3::new(self.radius, self.radius, self.radius);
+        let power = luminance(&self.radiance) * PI * 4. * PI * self.radius * self.radius;
+        Some(LightBounds::omnidirectional(self.center - extent, self.center + extent, power))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::random_unit_vector;
+
+    #[test]
+    fn blackbody_samples_carry_their_spectrum() {
+        let p = Vec3::new(0., 0., 0.);
+        let point = PointLight::blackbody(Vec3::new(0., 2., 0.), 2700., Intensity::Watts(60.));
+        let sphere = SphereLight::blackbody(Vec3::new(0., 3., 0.), 0.5, 5000., Intensity::Lumens(800.));
+        for sample in [point.sample(&p).unwrap(), sphere.sample(&p).unwrap()].iter() {
+            let (spectrum, factor) = sample.spectrum.unwrap();
+            let rgb = spectrum.to_rgb() * factor;
+            for i in 0..3 {
+                assert!((rgb.e[i] / sample.radiance.e[i] - 1.).abs() < 1e-9);
+            }
+        }
+        assert!(PointLight::new(p, Vec3::new(1., 1., 1.)).sample(&Vec3::new(1., 0., 0.)).unwrap().spectrum.is_none());
+    }
+
+    #[test]
+    fn union_cone_contains_both_cones() {
+        for _ in 0..1000 {
+            let a = random_unit_vector();
+            let b = random_unit_vector();
+            let theta_a = rand() * PI;
+            let theta_b = rand() * PI;
+            let (axis, theta_o) = union_cone(a, theta_a, b, theta_b);
+            assert!((axis.length() - 1.).abs() < 1e-9);
+            for &(v, theta) in [(a, theta_a), (b, theta_b)].iter() {
+                let angle = Vec3::dot(&axis, &v).clamp(-1., 1.).acos();
+                assert!((angle + theta).min(PI) <= theta_o + 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn importance_of_lights() {
+        let p = Vec3::new(0., 0., 0.);
+        let point = PointLight::new(p, Vec3::new(1., 1., 1.)).bounds().unwrap();
+        assert_eq!(point.importance(&p), 0.);
+        assert!(point.importance(&Vec3::new(0., 1., 0.)) > 0.);
+
+        let spot = SpotLight::new(p, Vec3::new(0., -1., 0.), Vec3::new(1., 1., 1.), 30., 30.).bounds().unwrap();
+        assert!(spot.importance(&Vec3::new(0., -1., 0.)) > 0.);
+        assert!(spot.importance(&Vec3::new(0.1, -1., 0.)) > 0.);
+        assert_eq!(spot.importance(&Vec3::new(0., 1., 0.)), 0.);
+    }
+}
@@ -0,0 +1,224 @@
+use vec3::Vec3;
+use common::rand;
+use light::{Light, LightSample, LightBounds};
+
+type BoxedLight = Box<dyn Light + Sync>;
+
+struct Node {
+    bounds: LightBounds,
+    /// The children of an interior node, or `None` for a leaf.
+    children: Option<(usize, usize)>,
+    light: usize,
+}
+
+/// The lights of a scene, with the bounded ones organized in a bounding
+/// volume hierarchy so that one of them can be picked in proportion to its
+/// estimated contribution at a shading point, in time logarithmic in the
+/// number of lights. Lights at infinity are sampled every time.
+pub struct LightTree {
+    lights: Vec<BoxedLight>,
+    infinite: Vec<usize>,
+    nodes: Vec<Node>,
+}
+
+impl LightTree {
+    pub fn new(lights: Vec<BoxedLight>) -> LightTree {
+        let mut infinite = Vec::new();
+        let mut bounded = Vec::new();
+        for (i, light) in lights.iter().enumerate() {
+            match light.bounds() {
+                Some(bounds) => bounded.push((i, bounds)),
+                None => infinite.push(i),
+            }
+        }
+
+        let mut nodes = Vec::with_capacity(2 * bounded.len());
+        if !bounded.is_empty() {
+            build(&mut nodes, &mut bounded);
+        }
+        LightTree {
+            lights,
+            infinite,
+            nodes,
+        }
+    }
+
+    /// Samples every light at infinity and one of the other lights, whose
+    /// radiance is divided by the probability of picking it. Also returns
+    /// whether one of the other lights was picked, which fails where none
+    /// of them is estimated to contribute.
+    pub fn sample(&self, p: &Vec3) -> (Vec<LightSample>, bool) {
+        let mut samples: Vec<LightSample> = self.infinite.iter()
+                                                .filter_map(|&i| self.lights[i].sample(p))
+                                                .collect();
+        let picked = self.pick(p);
+        if let Some((light, probability)) = picked {
+            if let Some(mut sample) = self.lights[light].sample(p) {
+                sample.radiance /= probability;
+                if let Some((_, ref mut factor)) = sample.spectrum {
+                    *factor /= probability;
+                }
+                samples.push(sample);
+            }
+        }
+        (samples, picked.is_some())
+    }
+
+    /// Radiance seen along a ray leaving the scene in `direction`.
+    pub fn emitted(&self, direction: &Vec3) -> Vec3 {
+        let mut radiance = Vec3::new(0., 0., 0.);
+        for &i in &self.infinite {
+            radiance += self.lights[i].emitted(direction);
+        }
+        radiance
+    }
+
+    /// Walks down the tree choosing children by importance at `p`.
+    fn pick(&self, p: &Vec3) -> Option<(usize, f64)> {
+        let mut node = self.nodes.first()?;
+        let mut probability = 1.;
+        while let Some((left, right)) = node.children {
+            let l = self.nodes[left].bounds.importance(p);
+            let r = self.nodes[right].bounds.importance(p);
+            if l + r <= 0. {
+                return None;
+            }
+            let p_left = l / (l + r);
+            if rand() < p_left {
+                probability *= p_left;
+                node = &self.nodes[left];
+            } else {
+                probability *= 1. - p_left;
+                node = &self.nodes[right];
+            }
+        }
+        Some((node.light, probability))
+    }
+}
+
+/// Appends the subtree over `lights` to `nodes` and returns its index,
+/// splitting at the median centroid along the widest axis.
+fn build(nodes: &mut Vec<Node>, lights: &mut [(usize, LightBounds)]) -> usize {
+    let index = nodes.len();
+    if lights.len() == 1 {
+        nodes.push(Node {
+            bounds: lights[0].1,
+            children: None,
+            light: lights[0].0,
+        });
+        return index;
+    }
+
+    let bounds = lights[1..].iter().fold(lights[0].1, |b, l| b.union(&l.1));
+    nodes.push(Node {
+        bounds,
+        children: None,
+        light: 0,
+    });
+
+    let centroids = lights.iter().map(|l| l.1.centroid());
+    let (min, max) = centroids.fold((Vec3::new(f64::MAX, f64::MAX, f64::MAX), Vec3::new(f64::MIN, f64::MIN, f64::MIN)),
+                                    |(min, max), c| {
+                                        (Vec3::new(min.e[0].min(c.e[0]), min.e[1].min(c.e[1]), min.e[2].min(c.e[2])),
+                                         Vec3::new(max.e[0].max(c.e[0]), max.e[1].max(c.e[1]), max.e[2].max(c.e[2])))
+                                    });
+    let extent = max - min;
+    let axis = if extent.e[0] >= extent.e[1] && extent.e[0] >= extent.e[2] {
+        0
+    } else if extent.e[1] >= extent.e[2] {
+        1
+    } else {
+        2
+    };
+    lights.sort_by(|a, b| a.1.centroid().e[axis].partial_cmp(&b.1.centroid().e[axis]).unwrap());
+
+    let (left, right) = lights.split_at_mut(lights.len() / 2);
+    let left = build(nodes, left);
+    let right = build(nodes, right);
+    nodes[index].children = Some((left, right));
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use light::{PointLight, SpotLight};
+
+    /// The probability of picking each light, by walking every branch.
+    fn probabilities(tree: &LightTree, p: &Vec3) -> Vec<f64> {
+        let mut result = vec![0.; tree.lights.len()];
+        let mut stack = vec![(0, 1.)];
+        while let Some((index, probability)) = stack.pop() {
+            let node = &tree.nodes[index];
+            match node.children {
+                None => result[node.light] += probability,
+                Some((left, right)) => {
+                    let l = tree.nodes[left].bounds.importance(p);
+                    let r = tree.nodes[right].bounds.importance(p);
+                    if l + r > 0. {
+                        stack.push((left, probability * l / (l + r)));
+                        stack.push((right, probability * r / (l + r)));
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    fn point(x: f64, y: f64, z: f64, power: f64) -> BoxedLight {
+        Box::new(PointLight::new(Vec3::new(x, y, z), Vec3::new(power, power, power)))
+    }
+
+    #[test]
+    fn pick_probabilities_sum_to_one() {
+        let lights = (0..7).map(|i| point(i as f64, (i * i) as f64 * 0.1, -1., 1. + i as f64)).collect();
+        let tree = LightTree::new(lights);
+        let p = Vec3::new(0.5, 2., 0.5);
+        let expected = probabilities(&tree, &p);
+        assert!((expected.iter().sum::<f64>() - 1.).abs() < 1e-12);
+
+        let mut counts = vec![0; expected.len()];
+        const N: usize = 20000;
+        for _ in 0..N {
+            let (light, probability) = tree.pick(&p).unwrap();
+            assert!((probability - expected[light]).abs() < 1e-12);
+            counts[light] += 1;
+        }
+        for (count, expected) in counts.iter().zip(expected.iter()) {
+            assert!((*count as f64 / N as f64 - expected).abs() < 0.02);
+        }
+    }
+
+    #[test]
+    fn skips_subtrees_without_importance() {
+        let away = |x: f64| -> BoxedLight {
+            Box::new(SpotLight::new(Vec3::new(x, 0., 0.), Vec3::new(0., 1., 0.), Vec3::new(1., 1., 1.), 10., 20.))
+        };
+        let p = Vec3::new(0., -2., 0.);
+        let tree = LightTree::new(vec![away(0.), away(1.), point(10., 0., 0., 1.)]);
+        assert_eq!(probabilities(&tree, &p)[2], 1.);
+        for _ in 0..100 {
+            assert_eq!(tree.pick(&p), Some((2, 1.)));
+        }
+
+        let tree = LightTree::new(vec![away(0.), away(1.)]);
+        assert_eq!(tree.pick(&p), None);
+        let (samples, picked) = tree.sample(&p);
+        assert!(samples.is_empty() && !picked);
+    }
+
+    #[test]
+    fn picks_hard_edged_spot() {
+        let spot: BoxedLight = Box::new(SpotLight::new(Vec3::new(0., 2., 0.), Vec3::new(0., -1., 0.),
+                                                       Vec3::new(1., 1., 1.), 30., 30.));
+        let tree = LightTree::new(vec![spot, point(5., 2., 0., 1.)]);
+        let p = Vec3::new(0., 0., 0.);
+        let probability = probabilities(&tree, &p)[0];
+        assert!(probability > 0.);
+        let picks = (0..1000).filter(|_| tree.pick(&p).unwrap().0 == 0).count();
+        assert!(picks > 0);
+
+        let outside = Vec3::new(0., 4., 0.);
+        assert_eq!(probabilities(&tree, &outside)[0], 0.);
+    }
+}
@@ -1,5 +1,6 @@
 extern crate raytracing;
 extern crate rayon;
+extern crate rand;
 
 use std::fs::File;
 use std::io::Write;
@@ -11,29 +12,279 @@ use rayon::prelude::*;
 
 use raytracing::vec3::Vec3;
 use raytracing::ray::Ray;
-use raytracing::camera::Camera;
+use raytracing::camera::{Camera, Projection};
+use raytracing::camera::stereo::{StereoCamera, Eye};
 use raytracing::common::rand;
-use raytracing::hitable::{World, Hitable};
+use raytracing::hitable::{World, Hitable, HitRecord};
+use raytracing::light::{DirectionalLight, LightSample};
+use raytracing::light::tree::LightTree;
+use raytracing::atmosphere::Atmosphere;
+use raytracing::environment::{Environment, Gradient};
+use raytracing::spectrum::{RgbSpectrum, HERO_COUNT, sample_hero_wavelengths, spectral_to_xyz, xyz_to_output};
+use raytracing::material::Lobe;
 use raytracing::hitable::surfaces::sphere::Sphere;
 use raytracing::material::common::lambertian::Lambertian;
 use raytracing::material::common::metal::Metal;
 use raytracing::material::common::dielectric::Dielectric;
 
-fn colour<'a, T>(r: Ray, world: &T, depth: usize) -> Vec3
-where
-    T: Hitable<'a>
-{
-    if let Some(hrec) = world.hit(&r, 0.0001, std::f64::MAX)  {
-        if depth < 50 {
-            if let Some((att, scat)) = hrec.material.scatter(&r, &hrec) {
-                return att * colour(scat, world, depth + 1);
+/// The lights sampled directly at the previous vertex of a path, whose
+/// emission is not added again when the path reaches them.
+#[derive(Copy, Clone)]
+struct Sampled {
+    /// The lights at infinity, seen by rays leaving the scene.
+    infinite: bool,
+    /// The lights with geometry, seen on surfaces that are lights.
+    bounded: bool,
+}
+
+impl Sampled {
+    const NONE: Sampled = Sampled { infinite: false, bounded: false };
+}
+
+/// How far a path has come, carried from one vertex to the next.
+#[derive(Copy, Clone)]
+struct Path {
+    depth: usize,
+    /// The steps taken by the current random walk inside a medium.
+    walk: usize,
+    sampled: Sampled,
+}
+
+impl Path {
+    const MAX_DEPTH: usize = 50;
+    const MAX_WALK: usize = 256;
+
+    const START: Path = Path { depth: 0, walk: 0, sampled: Sampled::NONE };
+
+    /// The path after scattering at a vertex, or `None` once it is too long.
+    fn bounce(self, sampled: Sampled) -> Option<Path> {
+        if self.depth < Path::MAX_DEPTH {
+            Some(Path { depth: self.depth + 1, walk: 0, sampled })
+        } else {
+            None
+        }
+    }
+
+    /// The path after a step of a random walk, which keeps the lights that
+    /// were sampled where the walk was entered.
+    fn step(self) -> Option<Path> {
+        if self.walk < Path::MAX_WALK {
+            Some(Path { walk: self.walk + 1, ..self })
+        } else {
+            None
+        }
+    }
+
+    /// The path after scattering from `lobe` at a surface where the lights
+    /// in `sampled` were sampled directly.
+    fn scatter(self, lobe: Lobe, sampled: Option<Sampled>) -> Option<Path> {
+        match (lobe, sampled) {
+            (Lobe::Walk, _) => self.step(),
+            (Lobe::Evaluated, Some(sampled)) => self.bounce(sampled),
+            _ => self.bounce(Sampled::NONE),
+        }
+    }
+}
+
+struct Scene<'a> {
+    world: World<'a>,
+    lights: LightTree,
+    atmosphere: Option<Atmosphere>,
+    environment: Option<Box<dyn Environment + Sync>>,
+}
+
+impl<'a> Scene<'a> {
+    /// The fraction of light travelling `distance` from `p` along `direction`
+    /// that is neither blocked by surfaces nor absorbed by the atmosphere.
+    fn visibility(&self, r: &Ray, p: Vec3, direction: Vec3, distance: f64) -> f64 {
+        let shadow = r.spawn(p, direction);
+        let mut visibility = self.world.transmittance(&shadow, 0.0001, distance);
+        if let Some(ref atmosphere) = self.atmosphere {
+            visibility *= atmosphere.transmittance(&shadow, distance);
+        }
+        visibility
+    }
+
+    /// Samples the light sources from `p`, returning the samples that are
+    /// not blocked with their visibility, and the lights that were sampled.
+    fn light_samples(&self, r: &Ray, p: Vec3) -> (Vec<(LightSample, f64)>, Sampled) {
+        let (samples, picked) = self.lights.sample(&p);
+        let visible = samples.into_iter()
+                             .map(|sample| {
+                                 let visibility = self.visibility(r, p, sample.direction, sample.distance);
+                                 (sample, visibility)
+                             })
+                             .filter(|&(_, visibility)| visibility > 0.)
+                             .collect();
+        (visible, Sampled { infinite: true, bounded: picked })
+    }
+
+    /// Light arriving at `p` directly from the light sources, weighted by
+    /// `response` for the direction it arrives from, with the lights that
+    /// were sampled.
+    fn direct<F>(&self, r: &Ray, p: Vec3, response: F) -> (Vec3, Sampled)
+    where
+        F: Fn(&Vec3) -> Vec3
+    {
+        let (samples, sampled) = self.light_samples(r, p);
+        let mut direct = Vec3::new(0., 0., 0.);
+        for (sample, visibility) in samples {
+            direct += response(&sample.direction) * sample.radiance * visibility;
+        }
+        (direct, sampled)
+    }
+
+    /// Like `direct`, at the wavelengths `lambdas`. Lights with an emission
+    /// spectrum are evaluated at each wavelength, the light of the others is
+    /// upsampled from RGB.
+    fn direct_spectral<F>(&self, r: &Ray, p: Vec3, lambdas: &[f64; HERO_COUNT], response: F)
+                          -> ([f64; HERO_COUNT], Sampled)
+    where
+        F: Fn(&Vec3) -> Vec3
+    {
+        let (samples, sampled) = self.light_samples(r, p);
+        let mut direct = [0.; HERO_COUNT];
+        let mut rgb = Vec3::new(0., 0., 0.);
+        for (sample, visibility) in samples {
+            let response = response(&sample.direction) * visibility;
+            match sample.spectrum {
+                Some((spectrum, factor)) => {
+                    let response = RgbSpectrum::reflectance(&response);
+                    for k in 0..HERO_COUNT {
+                        direct[k] += response.eval(lambdas[k]) * spectrum.eval(lambdas[k]) * factor;
+                    }
+                }
+                None => rgb += response * sample.radiance,
+            }
+        }
+        let rgb = RgbSpectrum::unbounded(&rgb);
+        for k in 0..HERO_COUNT {
+            direct[k] += rgb.eval(lambdas[k]);
+        }
+        (direct, sampled)
+    }
+
+    /// Light arriving directly at a surface hit, or `None` if its material
+    /// cannot be evaluated and it only receives the light it scatters
+    /// towards.
+    fn direct_surface(&self, r: &Ray, hrec: &HitRecord) -> Option<(Vec3, Sampled)> {
+        hrec.material.eval(r, hrec, &hrec.normal)?;
+        let zero = Vec3::new(0., 0., 0.);
+        Some(self.direct(r, hrec.p, |l| hrec.material.eval(r, hrec, l).unwrap_or(zero)))
+    }
+
+    /// Like `direct_surface`, at the wavelengths `lambdas`.
+    fn direct_surface_spectral(&self, r: &Ray, hrec: &HitRecord, lambdas: &[f64; HERO_COUNT])
+                               -> Option<([f64; HERO_COUNT], Sampled)> {
+        hrec.material.eval(r, hrec, &hrec.normal)?;
+        let zero = Vec3::new(0., 0., 0.);
+        Some(self.direct_spectral(r, hrec.p, lambdas, |l| hrec.material.eval(r, hrec, l).unwrap_or(zero)))
+    }
+
+    /// Radiance arriving along a ray that leaves the scene. Lights that were
+    /// sampled at the previous vertex were already accounted for.
+    fn escaped(&self, r: &Ray, sampled: Sampled) -> Vec3 {
+        let mut radiance = self.environment.as_ref()
+                               .map_or(Vec3::new(0., 0., 0.), |e| e.radiance(&r.direction));
+        if !sampled.infinite {
+            radiance += self.lights.emitted(&r.direction);
+        }
+        radiance
+    }
+
+    /// Samples a scattering event in the atmosphere in front of the first
+    /// surface hit, returning the point it happens at. The light scattered
+    /// there is the light arriving directly from the light sources weighted
+    /// by the phase function the closure returns, times the albedo.
+    fn scatter_atmosphere(&self, r: &Ray, t_max: f64) -> Option<(Vec3, impl Fn(&Vec3) -> Vec3 + '_, Ray)> {
+        let atmosphere = self.atmosphere.as_ref()?;
+        let t = atmosphere.sample_distance(r, t_max)?;
+        let p = r.point_at_parameter(t);
+        let direction = r.direction.unit_vector();
+        let phase = move |l: &Vec3| {
+            let phase = atmosphere.phase(Vec3::dot(&direction, l));
+            Vec3::new(phase, phase, phase)
+        };
+        Some((p, phase, r.spawn(p, atmosphere.sample_direction(&direction))))
+    }
+}
+
+fn colour(r: Ray, scene: &Scene, path: Path) -> Vec3 {
+    let hit = scene.world.hit(&r, 0.0001, f64::MAX);
+    let t_max = hit.as_ref().map_or(f64::MAX, |hrec| hrec.t);
+    if let Some((p, phase, scattered)) = scene.scatter_atmosphere(&r, t_max) {
+        let albedo = scene.atmosphere.as_ref().unwrap().albedo();
+        let (direct, sampled) = scene.direct(&r, p, phase);
+        if let Some(path) = path.bounce(sampled) {
+            return albedo * (direct + colour(scattered, scene, path));
+        }
+        return albedo * direct;
+    }
+
+    if let Some(hrec) = hit {
+        let emitted = if path.sampled.bounded && hrec.material.is_light() {
+            Vec3::new(0., 0., 0.)
+        } else {
+            hrec.material.emitted(&r, &hrec)
+        };
+        let direct = scene.direct_surface(&r, &hrec);
+        let radiance = emitted + direct.map_or(Vec3::new(0., 0., 0.), |d| d.0);
+        if let Some((att, scat, lobe)) = hrec.material.scatter_lobe(&r, &hrec) {
+            if let Some(path) = path.scatter(lobe, direct.map(|d| d.1)) {
+                return radiance + att * colour(scat, scene, path);
+            }
+        }
+        return radiance;
+    }
+    scene.escaped(&r, path.sampled)
+}
+
+fn colour_spectral(r: Ray, lambdas: &[f64; HERO_COUNT], scene: &Scene, path: Path) -> [f64; HERO_COUNT] {
+    let mut radiance = [0.; HERO_COUNT];
+    let hit = scene.world.hit(&r, 0.0001, f64::MAX);
+    let t_max = hit.as_ref().map_or(f64::MAX, |hrec| hrec.t);
+    if let Some((p, phase, scattered)) = scene.scatter_atmosphere(&r, t_max) {
+        let albedo = RgbSpectrum::reflectance(&scene.atmosphere.as_ref().unwrap().albedo());
+        let (direct, sampled) = scene.direct_spectral(&r, p, lambdas, phase);
+        let incoming = path.bounce(sampled).map(|path| colour_spectral(scattered, lambdas, scene, path));
+        for k in 0..HERO_COUNT {
+            radiance[k] = albedo.eval(lambdas[k]) * (direct[k] + incoming.map_or(0., |incoming| incoming[k]));
+        }
+        return radiance;
+    }
+
+    if let Some(hrec) = hit {
+        let direct = scene.direct_surface_spectral(&r, &hrec, lambdas);
+        let emits = !(path.sampled.bounded && hrec.material.is_light());
+        for k in 0..HERO_COUNT {
+            let emitted = if emits { hrec.material.emitted_spectral(&r, &hrec, lambdas[k]) } else { 0. };
+            radiance[k] = emitted + direct.map_or(0., |d| d.0[k]);
+        }
+        // Dispersion makes the secondary wavelengths follow different
+        // paths, so only the hero wavelength carries on.
+        let collapse = r.wavelength.is_none() && hrec.material.is_dispersive();
+        let r = if collapse { r.with_wavelength(lambdas[0]) } else { r };
+        if let Some((att, scat, lobe)) = hrec.material.scatter_lobe(&r, &hrec) {
+            if let Some(path) = path.scatter(lobe, direct.map(|d| d.1)) {
+                let att = RgbSpectrum::reflectance(&att);
+                let incoming = colour_spectral(scat, lambdas, scene, path);
+                for k in 0..HERO_COUNT {
+                    let weight = match (collapse, k) {
+                        (false, _) => 1.,
+                        (true, 0) => HERO_COUNT as f64,
+                        (true, _) => 0.,
+                    };
+                    radiance[k] += weight * att.eval(lambdas[k]) * incoming[k];
+                }
             }
         }
-        return Vec3::new(0., 0., 0.);
+        return radiance;
+    }
+    let sky = RgbSpectrum::unbounded(&scene.escaped(&r, path.sampled));
+    for k in 0..HERO_COUNT {
+        radiance[k] = sky.eval(lambdas[k]);
     }
-    let unit_direction = r.direction.unit_vector();
-    let t = (unit_direction.e[1] + 1.) * 0.5;
-    Vec3::new(1., 1., 1.) * (1. - t) + Vec3::new(0.5, 0.7, 1.) * t
+    radiance
 }
 
 fn random_scene<'a>() -> World<'a> {
@@ -83,49 +334,36 @@ fn random_scene<'a>() -> World<'a> {
     World::new(objects)
 }
 
-fn main() {
-    const NX: u32 = 800;
-    const NY: u32 = 400;
-    const NS: u32 = 100;
-
-    let R: f64 = (PI / 4.).cos();
-
-    let mut gen = OsRng::new().unwrap();
-
-    let world = random_scene();
-    
-    let lookfrom = Vec3::new(13., 2., 3.);
-    let lookat = Vec3::new(0., 0., 0.);
-    let focus_dist = 10.;
-    let camera = Camera::new(lookfrom,
-                             lookat,
-                             Vec3::new(0., 1., 0.),
-                             20., NX as f64 / NY as f64,
-                             0.,
-                             focus_dist);
-
-    let mut file = File::create("test.ppm").unwrap();
+/// Renders `camera` to a PPM image at `path`.
+fn render(camera: &(dyn Projection + Sync), scene: &Scene, nx: u32, ny: u32, ns: u32, spectral: bool, path: &str) {
+    let mut file = File::create(path).unwrap();
 
-    file.write_fmt(format_args!("P3\n{} {}\n255\n", NX, NY)).unwrap();
-    for j in (0..NY).rev() {
+    file.write_fmt(format_args!("P3\n{} {}\n255\n", nx, ny)).unwrap();
+    for j in (0..ny).rev() {
         println!("{}", j);
-        let mut v = Vec::with_capacity(NX as usize);
-        v.resize(NX as usize, (Vec3::new(0., 0., 0.), 0, 0, 0));
+        let mut v = Vec::with_capacity(nx as usize);
+        v.resize(nx as usize, (Vec3::new(0., 0., 0.), 0, 0, 0));
         let v: Vec<(Vec3, u8, u8, u8)> = v.into_par_iter().enumerate().map(|(i, _)| {
             let mut col = Vec3::new(0., 0., 0.);
-            for _ in 0..NS {
+            for _ in 0..ns {
                 let mut gen = OsRng::new().unwrap();
                 let rand_u: f64 = gen.gen();
                 let rand_v: f64 = gen.gen();
-                let u = ((i as f64) + rand_u) / (NX as f64);
-                let v = ((j as f64) + rand_v) / (NY as f64);
+                let u = ((i as f64) + rand_u) / (nx as f64);
+                let v = ((j as f64) + rand_v) / (ny as f64);
 
                 let r = camera.get_ray(u, v);
 
-                col += colour(r, &world, 0);
+                if spectral {
+                    let lambdas = sample_hero_wavelengths();
+                    let radiance = colour_spectral(r, &lambdas, scene, Path::START);
+                    col += xyz_to_output(&spectral_to_xyz(&lambdas, &radiance));
+                } else {
+                    col += colour(r, scene, Path::START);
+                }
             }
 
-            col /= NS as f64;
+            col *= camera.exposure() / ns as f64;
 
             let col = Vec3::new(col.e[0].sqrt(), col.e[1].sqrt(), col.e[2].sqrt());
             let ir = (255.99*col.e[0]) as u8;
@@ -139,3 +377,56 @@ fn main() {
         }
     }
 }
+
+fn main() {
+    const NX: u32 = 800;
+    const NY: u32 = 400;
+    const NS: u32 = 100;
+    const SPECTRAL: bool = false;
+    const STEREO: bool = false;
+    const SUN_AND_HAZE: bool = false;
+
+    let R: f64 = (PI / 4.).cos();
+
+    let mut gen = OsRng::new().unwrap();
+
+    let scene = Scene {
+        world: random_scene(),
+        lights: if SUN_AND_HAZE {
+            LightTree::new(vec![Box::new(DirectionalLight::new(Vec3::new(-1., 2., 1.), Vec3::new(1., 0.95, 0.85)))])
+        } else {
+            LightTree::new(Vec::new())
+        },
+        atmosphere: if SUN_AND_HAZE {
+            Some(Atmosphere::new(0.015, 0., 1.5, Vec3::new(0.9, 0.9, 0.9), 0.3))
+        } else {
+            None
+        },
+        environment: Some(Box::new(Gradient::new(Vec3::new(1., 1., 1.), Vec3::new(0.5, 0.7, 1.)))),
+    };
+    
+    let lookfrom = Vec3::new(13., 2., 3.);
+    let lookat = Vec3::new(0., 0., 0.);
+    let focus_dist = 10.;
+
+    if STEREO {
+        // The interocular distance follows the 1/30 rule of stereography.
+        let camera = StereoCamera::parallel(lookfrom,
+                                            lookat,
+                                            Vec3::new(0., 1., 0.),
+                                            20., NX as f64 / NY as f64,
+                                            focus_dist / 30.,
+                                            focus_dist);
+        render(camera.eye(Eye::Left), &scene, NX, NY, NS, SPECTRAL, "left.ppm");
+        render(camera.eye(Eye::Right), &scene, NX, NY, NS, SPECTRAL, "right.ppm");
+        return;
+    }
+
+    let camera: Box<dyn Projection + Sync> = Box::new(Camera::new(lookfrom,
+                                                              lookat,
+                                                              Vec3::new(0., 1., 0.),
+                                                              20., NX as f64 / NY as f64,
+                                                              0.,
+                                                              focus_dist));
+    render(&*camera, &scene, NX, NY, NS, SPECTRAL, "test.ppm");
+}
@@ -0,0 +1,162 @@
+use vec3::Vec3;
+use ray::Ray;
+use common::{SPECULAR_ALPHA, fresnel_dielectric, sample_ggx, ggx_d, ggx_g1, reflect, beer_lambert, rand};
+use material::{Material, Lobe};
+use hitable::HitRecord;
+
+/// A thin, smooth or rough dielectric coating over any `base` material, such
+/// as varnish over wood or clear coat over car paint. Light either reflects
+/// off the coating, chosen by its Fresnel reflectance, or passes through it,
+/// scatters off the base and leaves through the coating again. Light that is
+/// reflected back inside the coating is not traced further.
+#[derive(Copy, Clone)]
+pub struct Coated<T>
+where
+    T: Material + Copy + Clone
+{
+    base: T,
+    ref_idx: f64,
+    alpha: f64,
+    absorption: Vec3,
+}
+
+impl<T> Coated<T>
+where
+    T: Material + Copy + Clone
+{
+    pub fn new(base: T, ref_idx: f64, roughness: f64) -> Coated<T> {
+        Coated::with_absorption(base, ref_idx, roughness, Vec3::new(0., 0., 0.))
+    }
+
+    /// A tinted coating, where `absorption` is the optical depth of the
+    /// coating for light crossing it at normal incidence.
+    pub fn with_absorption(base: T, ref_idx: f64, roughness: f64, absorption: Vec3) -> Coated<T> {
+        Coated {
+            base,
+            ref_idx,
+            alpha: roughness * roughness,
+            absorption,
+        }
+    }
+
+    /// The cosine of a direction with cosine `cosine` after refracting into
+    /// the coating.
+    fn refracted_cosine(&self, cosine: f64) -> f64 {
+        (1. - (1. - cosine * cosine) / (self.ref_idx * self.ref_idx)).sqrt()
+    }
+}
+
+impl<T> Material for Coated<T>
+where
+    T: Material + Copy + Clone
+{
+    fn scatter(&self, r_in: &Ray, hrec: &HitRecord) -> Option<(Vec3, Ray)> {
+        self.scatter_lobe(r_in, hrec).map(|(attenuation, scattered, _)| (attenuation, scattered))
+    }
+
+    fn scatter_lobe(&self, r_in: &Ray, hrec: &HitRecord) -> Option<(Vec3, Ray, Lobe)> {
+        let view = -r_in.direction.unit_vector();
+        let n = hrec.normal;
+        let cos_v = Vec3::dot(&view, &n);
+        if cos_v <= 0. {
+            return self.base.scatter_lobe(r_in, hrec);
+        }
+
+        let reflect_probability = fresnel_dielectric(cos_v, self.ref_idx);
+        if rand() < reflect_probability {
+            if self.alpha <= SPECULAR_ALPHA {
+                let reflected = reflect(&r_in.direction, &n);
+                return Some((Vec3::new(1., 1., 1.), r_in.spawn(hrec.p, reflected), Lobe::Delta));
+            }
+            let m = sample_ggx(&n, self.alpha);
+            let cos_vm = Vec3::dot(&view, &m);
+            let l = m * (2. * cos_vm) - view;
+            if cos_vm <= 0. || Vec3::dot(&l, &n) <= 0. {
+                return None;
+            }
+            let g = ggx_g1(&view, &m, &n, self.alpha) * ggx_g1(&l, &m, &n, self.alpha);
+            let weight = fresnel_dielectric(cos_vm, self.ref_idx) * g * cos_vm
+                         / (cos_v * Vec3::dot(&m, &n) * reflect_probability);
+            return Some((Vec3::new(weight, weight, weight), r_in.spawn(hrec.p, l), Lobe::Evaluated));
+        }
+
+        let (attenuation, scattered, lobe) = self.base.scatter_lobe(r_in, hrec)?;
+        let cos_l = Vec3::dot(&scattered.direction.unit_vector(), &n);
+        if cos_l <= 0. {
+            return Some((attenuation, scattered, lobe));
+        }
+        let depth = self.refracted_cosine(cos_v).recip() + self.refracted_cosine(cos_l).recip();
+        let transmittance = beer_lambert(&self.absorption, depth)
+                            * (1. - fresnel_dielectric(cos_l, self.ref_idx));
+        Some((attenuation * transmittance, scattered, lobe))
+    }
+
+    fn eval(&self, r_in: &Ray, hrec: &HitRecord, direction: &Vec3) -> Option<Vec3> {
+        let view = -r_in.direction.unit_vector();
+        let n = hrec.normal;
+        let cos_v = Vec3::dot(&view, &n);
+        let cos_l = Vec3::dot(direction, &n);
+        if cos_v <= 0. {
+            return self.base.eval(r_in, hrec, direction);
+        }
+
+        let base = self.base.eval(r_in, hrec, direction).map(|base| {
+            if cos_l <= 0. {
+                return base;
+            }
+            let depth = self.refracted_cosine(cos_v).recip() + self.refracted_cosine(cos_l).recip();
+            base * beer_lambert(&self.absorption, depth)
+                 * ((1. - fresnel_dielectric(cos_v, self.ref_idx)) * (1. - fresnel_dielectric(cos_l, self.ref_idx)))
+        });
+        if self.alpha <= SPECULAR_ALPHA || cos_l <= 0. {
+            return base;
+        }
+
+        let h = (view + *direction).unit_vector();
+        let g = ggx_g1(&view, &h, &n, self.alpha) * ggx_g1(direction, &h, &n, self.alpha);
+        let coat = ggx_d(&h, &n, self.alpha) * fresnel_dielectric(Vec3::dot(&view, &h), self.ref_idx) * g
+                   / (4. * cos_v);
+        Some(base.unwrap_or(Vec3::new(0., 0., 0.)) + Vec3::new(coat, coat, coat))
+    }
+
+    fn emitted(&self, r_in: &Ray, hrec: &HitRecord) -> Vec3 {
+        self.base.emitted(r_in, hrec)
+    }
+
+    fn emitted_spectral(&self, r_in: &Ray, hrec: &HitRecord, wavelength: f64) -> f64 {
+        self.base.emitted_spectral(r_in, hrec, wavelength)
+    }
+
+    fn is_light(&self) -> bool {
+        self.base.is_light()
+    }
+
+    fn is_dispersive(&self) -> bool {
+        self.base.is_dispersive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use material::common::lambertian::Lambertian;
+    use material::testing::{hit, furnace, assert_reciprocal};
+
+    #[test]
+    fn conserves_energy() {
+        let white = Lambertian::new(Vec3::new(1., 1., 1.));
+        for &roughness in [0., 0.2, 0.6].iter() {
+            let hrec = hit(Coated::new(white, 1.5, roughness));
+            for &view in [Vec3::new(0., 0., 1.), Vec3::new(0.8, 0., 0.6), Vec3::new(0.99, 0., 0.141)].iter() {
+                let albedo = furnace(&hrec, &view.unit_vector());
+                assert!(albedo.e.iter().all(|&a| a > 0. && a <= 1.02), "{:?}", albedo.e);
+            }
+        }
+    }
+
+    #[test]
+    fn eval_is_reciprocal() {
+        let base = Lambertian::new(Vec3::new(0.3, 0.6, 0.9));
+        assert_reciprocal(&hit(Coated::with_absorption(base, 1.5, 0.4, Vec3::new(0.2, 0.1, 0.))));
+    }
+}
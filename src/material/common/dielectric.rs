@@ -1,41 +1,92 @@
 use vec3::Vec3;
 use ray::Ray;
-use common::{refract, reflect, schlick, rand};
+use common::{refract, reflect, schlick, rand, beer_lambert};
 use material::Material;
+use material::ior::Ior;
+use material::thin_film::{ThinFilm, Substrate};
 use hitable::HitRecord;
+use spectrum::{sample_wavelength, wavelength_to_rgb, REFERENCE_WAVELENGTH};
 
 #[derive(Copy, Clone)]
-pub struct Dielectric {
-    ref_idx: f64
+pub struct Dielectric<'a> {
+    ior: Ior,
+    absorption: Vec3,
+    film: Option<ThinFilm<'a>>,
 }
 
-impl Dielectric {
-    pub fn new(ref_idx: f64) -> Dielectric {
+impl<'a> Dielectric<'a> {
+    /// Creates a clear dielectric. Passing a dispersive `Ior` restricts each
+    /// path that hits it to a single wavelength, sampled on the first hit.
+    pub fn new<I: Into<Ior>>(ior: I) -> Dielectric<'a> {
+        Dielectric::with_absorption(ior, Vec3::new(0., 0., 0.))
+    }
+
+    /// A dielectric whose interior absorbs light following the Beer-Lambert
+    /// law, with `absorption` being the per channel coefficient per unit length.
+    pub fn with_absorption<I: Into<Ior>>(ior: I, absorption: Vec3) -> Dielectric<'a> {
+        Dielectric {
+            ior: ior.into(),
+            absorption,
+            film: None,
+        }
+    }
+
+    /// Coats the surface with a thin film, replacing its Fresnel reflectance
+    /// with that of the film stack.
+    pub fn with_thin_film(self, film: ThinFilm<'a>) -> Dielectric<'a> {
         Dielectric {
-            ref_idx,
+            film: Some(film),
+            ..self
         }
     }
+
+    /// A tinted dielectric that lets through `transmittance` of the light
+    /// after it has travelled `distance` units inside it. To keep the
+    /// absorption finite, `distance` and each channel of `transmittance` are
+    /// clamped to at least `1e-6`, and `transmittance` to at most 1.
+    pub fn tinted<I: Into<Ior>>(ior: I, transmittance: Vec3, distance: f64) -> Dielectric<'a> {
+        const EPSILON: f64 = 1e-6;
+        let absorption = |t: f64| -t.clamp(EPSILON, 1.).ln();
+        let absorption = Vec3::new(absorption(transmittance.e[0]),
+                                   absorption(transmittance.e[1]),
+                                   absorption(transmittance.e[2])) / distance.max(EPSILON);
+        Dielectric::with_absorption(ior, absorption)
+    }
 }
 
-impl Material for Dielectric {
+impl<'a> Material for Dielectric<'a> {
     fn scatter(&self, r_in: &Ray, hrec: &HitRecord) -> Option<(Vec3, Ray)> {
         let reflected = reflect(&r_in.direction, &hrec.normal);
-        let attenuation = Vec3::new(1., 1., 1.);
 
         //let mut outward_normal = Vec3::new(0., 0., 0.);
         //let n_ratio: f64 = 0.;
 
         let dot = Vec3::dot(&r_in.direction, &hrec.normal);
+        let attenuation = if dot.is_sign_positive() {
+            beer_lambert(&self.absorption, hrec.t * r_in.direction.length())
+        } else {
+            Vec3::new(1., 1., 1.)
+        };
+
+        let (attenuation, wavelength) = match r_in.wavelength {
+            None if self.ior.is_dispersive() => {
+                let wavelength = sample_wavelength();
+                (attenuation * wavelength_to_rgb(wavelength), Some(wavelength))
+            }
+            wavelength => (attenuation, wavelength),
+        };
+        let ref_idx = self.ior.at(wavelength.unwrap_or(REFERENCE_WAVELENGTH));
+        let scattered = |direction| Ray { wavelength, ..r_in.spawn(hrec.p, direction) };
         let (outward_normal, n_ratio, cosine) = if dot.is_sign_positive() {
             let outward_normal = -hrec.normal;
-            let n_ratio = self.ref_idx;
-            let cosine = self.ref_idx * Vec3::dot(&r_in.direction, &hrec.normal)
+            let n_ratio = ref_idx;
+            let cosine = ref_idx * Vec3::dot(&r_in.direction, &hrec.normal)
                          * r_in.direction.length().recip();
             (outward_normal, n_ratio, cosine)
         }
         else {
             let outward_normal = hrec.normal;
-            let n_ratio = self.ref_idx.recip();
+            let n_ratio = ref_idx.recip();
             let cosine = -Vec3::dot(&r_in.direction, &hrec.normal)
                          * r_in.direction.length().recip();
             (outward_normal, n_ratio, cosine)
@@ -43,14 +94,37 @@ impl Material for Dielectric {
 
         let res = refract(&r_in.direction, &outward_normal, n_ratio);
         if let Some(refracted) = res {
-            let reflect_probability = schlick(cosine, self.ref_idx);
+            if let Some(film) = self.film {
+                let (n1, n3) = if dot.is_sign_positive() { (ref_idx, 1.) } else { (1., ref_idx) };
+                let cos_i = dot.abs() * r_in.direction.length().recip();
+                let substrate = Substrate::Dielectric(n3);
+                let thickness = film.thickness(hrec);
+                let reflectance = match wavelength {
+                    Some(l) => {
+                        let r = film.reflectance(cos_i, n1, substrate, thickness, l);
+                        Vec3::new(r, r, r)
+                    }
+                    None => film.reflectance_rgb(cos_i, n1, [substrate; 3], thickness),
+                };
+                let reflect_probability = (reflectance.e[0] + reflectance.e[1] + reflectance.e[2]) / 3.;
+                if rand() < reflect_probability {
+                    return Some((attenuation * reflectance / reflect_probability, scattered(reflected)));
+                }
+                let transmittance = (Vec3::new(1., 1., 1.) - reflectance) / (1. - reflect_probability);
+                return Some((attenuation * transmittance, scattered(refracted)));
+            }
+            let reflect_probability = schlick(cosine, ref_idx);
             if rand() < reflect_probability {
-                return Some((attenuation, Ray::new(hrec.p, reflected)));
+                return Some((attenuation, scattered(reflected)));
             } else {
-                return Some((attenuation, Ray::new(hrec.p, refracted)));
+                return Some((attenuation, scattered(refracted)));
             }
         } else {
-            return Some((attenuation, Ray::new(hrec.p, reflected)));
+            return Some((attenuation, scattered(reflected)));
         }
     }
+
+    fn is_dispersive(&self) -> bool {
+        self.ior.is_dispersive() || self.film.is_some()
+    }
 }
@@ -0,0 +1,68 @@
+use std::f64::consts::PI;
+
+use vec3::Vec3;
+use ray::Ray;
+use material::Material;
+use hitable::HitRecord;
+use spectrum::{Spectrum, RgbSpectrum, Intensity};
+
+/// A light source emitting `spectrum` from its surface and absorbing all
+/// incoming light.
+#[derive(Copy, Clone)]
+pub struct Emissive {
+    spectrum: Spectrum,
+    rgb: Vec3,
+    light: bool,
+}
+
+impl Emissive {
+    pub fn new(spectrum: Spectrum) -> Emissive {
+        Emissive {
+            spectrum,
+            rgb: spectrum.to_rgb(),
+            light: false,
+        }
+    }
+
+    pub fn rgb(colour: Vec3) -> Emissive {
+        Emissive {
+            spectrum: Spectrum::Rgb(RgbSpectrum::unbounded(&colour)),
+            rgb: colour,
+            light: false,
+        }
+    }
+
+    /// A black body at `temperature` Kelvin, such as a filament or a hot
+    /// coal. Watts and lumens are emitted per square meter of surface.
+    pub fn blackbody(temperature: f64, intensity: Intensity) -> Emissive {
+        Emissive::new(intensity.blackbody(temperature, PI))
+    }
+
+    /// The radiance emitted when rendering in RGB.
+    pub fn rgb_radiance(&self) -> Vec3 {
+        self.rgb
+    }
+
+    /// Marks the surface as the geometry of one of the scene's lights.
+    pub fn as_light(self) -> Emissive {
+        Emissive { light: true, ..self }
+    }
+}
+
+impl Material for Emissive {
+    fn scatter(&self, _r_in: &Ray, _hrec: &HitRecord) -> Option<(Vec3, Ray)> {
+        None
+    }
+
+    fn emitted(&self, _r_in: &Ray, _hrec: &HitRecord) -> Vec3 {
+        self.rgb
+    }
+
+    fn emitted_spectral(&self, _r_in: &Ray, _hrec: &HitRecord, wavelength: f64) -> f64 {
+        self.spectrum.eval(wavelength)
+    }
+
+    fn is_light(&self) -> bool {
+        self.light
+    }
+}
@@ -1,8 +1,10 @@
+use std::f64::consts::PI;
+
 use vec3::Vec3;
 use ray::Ray;
 use material::Material;
 use hitable::HitRecord;
-use common::random_in_unit_sphere;
+use common::sample_cosine_hemisphere;
 
 #[derive(Copy, Clone)]
 pub struct Lambertian {
@@ -18,8 +20,27 @@ impl Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _r_in: &Ray, hrec: &HitRecord) -> Option<(Vec3, Ray)> {
-        let target = hrec.p + hrec.normal + random_in_unit_sphere();
-        Some((self.albedo, Ray::new(hrec.p, target - hrec.p)))
+    fn scatter(&self, r_in: &Ray, hrec: &HitRecord) -> Option<(Vec3, Ray)> {
+        let direction = sample_cosine_hemisphere(&hrec.normal);
+        Some((self.albedo, r_in.spawn(hrec.p, direction)))
+    }
+
+    fn eval(&self, _r_in: &Ray, hrec: &HitRecord, direction: &Vec3) -> Option<Vec3> {
+        let cosine = Vec3::dot(&hrec.normal, direction).max(0.);
+        Some(self.albedo * (cosine / PI))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use material::testing::{hit, scattered, evaluated, assert_close};
+
+    #[test]
+    fn eval_matches_scattered_rays() {
+        let hrec = hit(Lambertian::new(Vec3::new(0.2, 0.5, 0.8)));
+        let view = Vec3::new(0.6, 0., 0.8);
+        let g = |l: &Vec3| (1. + l.e[0]).powi(2);
+        assert_close(&evaluated(&hrec, &view, g), &scattered(&hrec, &view, g), 0.03);
     }
 }
@@ -1,31 +1,120 @@
+use std::f64::consts::PI;
+
 use vec3::Vec3;
 use ray::Ray;
 use hitable::HitRecord;
 use material::Material;
-use common::{random_in_unit_sphere, reflect};
+use material::thin_film::{ThinFilm, Substrate};
+use common::{SPECULAR_ALPHA, random_in_unit_sphere, reflect};
+use spectrum::RgbSpectrum;
 
 #[derive(Copy, Clone)]
-pub struct Metal {
+pub struct Metal<'a> {
     albedo: Vec3,
     fuzz: f64,
+    film: Option<ThinFilm<'a>>,
 }
 
-impl Metal {
-    pub fn new(albedo: Vec3, fuzz: f64) -> Metal {
+impl<'a> Metal<'a> {
+    pub fn new(albedo: Vec3, fuzz: f64) -> Metal<'a> {
         Metal {
             albedo,
             fuzz,
+            film: None,
+        }
+    }
+
+    /// Coats the metal with a thin film, such as an anodized oxide layer.
+    pub fn with_thin_film(self, film: ThinFilm<'a>) -> Metal<'a> {
+        Metal {
+            film: Some(film),
+            ..self
+        }
+    }
+
+    fn reflectance(&self, r_in: &Ray, hrec: &HitRecord) -> Vec3 {
+        let film = match self.film {
+            Some(film) => film,
+            None => return self.albedo,
+        };
+        let cos_i = Vec3::dot(&r_in.direction.unit_vector(), &hrec.normal).abs();
+        let thickness = film.thickness(hrec);
+        match r_in.wavelength {
+            Some(l) => {
+                let substrate = Substrate::Conductor(RgbSpectrum::reflectance(&self.albedo).eval(l));
+                let r = film.reflectance(cos_i, 1., substrate, thickness, l);
+                Vec3::new(r, r, r)
+            }
+            None => {
+                let substrates = [Substrate::Conductor(self.albedo.e[0]),
+                                  Substrate::Conductor(self.albedo.e[1]),
+                                  Substrate::Conductor(self.albedo.e[2])];
+                film.reflectance_rgb(cos_i, 1., substrates, thickness)
+            }
         }
     }
 }
 
-impl Material for Metal {
+impl<'a> Material for Metal<'a> {
     fn scatter(&self, r_in: &Ray, hrec: &HitRecord) -> Option<(Vec3, Ray)> {
         let reflected = reflect(&r_in.direction.unit_vector(), &hrec.normal);
-        let scattered = Ray::new(hrec.p, reflected + random_in_unit_sphere()*self.fuzz);
+        let scattered = r_in.spawn(hrec.p, reflected + random_in_unit_sphere()*self.fuzz);
         if Vec3::dot(&scattered.direction, &hrec.normal).is_sign_positive() {
-            return Some((self.albedo, scattered))
+            return Some((self.reflectance(r_in, hrec), scattered))
         }
         None
     }
+
+    /// Fuzzy reflection offsets the mirror direction by a point uniformly
+    /// distributed in a sphere of radius `fuzz`, so the density of a
+    /// direction is the volume of that sphere it passes through, seen from
+    /// the origin. A fuzz about as small as a mirror-like GGX lobe is left
+    /// to `scatter`.
+    fn eval(&self, r_in: &Ray, hrec: &HitRecord, direction: &Vec3) -> Option<Vec3> {
+        if self.fuzz <= SPECULAR_ALPHA {
+            return None;
+        }
+        let zero = Vec3::new(0., 0., 0.);
+        if Vec3::dot(direction, &hrec.normal) <= 0. {
+            return Some(zero);
+        }
+        let reflected = reflect(&r_in.direction.unit_vector(), &hrec.normal);
+        let c = Vec3::dot(direction, &reflected);
+        let discriminant = c * c - 1. + self.fuzz * self.fuzz;
+        if discriminant <= 0. {
+            return Some(zero);
+        }
+        let far = c + discriminant.sqrt();
+        let near = (c - discriminant.sqrt()).max(0.);
+        if far <= 0. {
+            return Some(zero);
+        }
+        let pdf = (far.powi(3) - near.powi(3)) / (4. * PI * self.fuzz.powi(3));
+        Some(self.reflectance(r_in, hrec) * pdf)
+    }
+
+    fn is_dispersive(&self) -> bool {
+        self.film.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use material::testing::{hit, ray_from, scattered, evaluated, assert_close};
+
+    #[test]
+    fn eval_matches_scattered_rays() {
+        let hrec = hit(Metal::new(Vec3::new(0.9, 0.6, 0.3), 0.8));
+        let view = Vec3::new(0.6, 0., 0.8);
+        let g = |l: &Vec3| (1. - l.e[0]).powi(2);
+        assert_close(&evaluated(&hrec, &view, g), &scattered(&hrec, &view, g), 0.03);
+    }
+
+    #[test]
+    fn leaves_mirrors_to_scatter() {
+        let hrec = hit(Metal::new(Vec3::new(0.9, 0.6, 0.3), 0.));
+        let view = Vec3::new(0.6, 0., 0.8);
+        assert!(hrec.material.eval(&ray_from(&view), &hrec, &Vec3::new(-0.6, 0., 0.8)).is_none());
+    }
 }
@@ -0,0 +1,115 @@
+use vec3::Vec3;
+use ray::Ray;
+use common::rand;
+use material::{Material, Lobe};
+use hitable::HitRecord;
+use texture::Texture;
+
+/// Blends two materials, scattering with `b` in proportion to `weight` and
+/// with `a` otherwise.
+#[derive(Copy, Clone)]
+pub struct MixMaterial<'a, A, B>
+where
+    A: Material + Copy + Clone,
+    B: Material + Copy + Clone
+{
+    a: A,
+    b: B,
+    weight: Texture<'a>,
+}
+
+impl<'a, A, B> MixMaterial<'a, A, B>
+where
+    A: Material + Copy + Clone,
+    B: Material + Copy + Clone
+{
+    pub fn new<T: Into<Texture<'a>>>(a: A, b: B, weight: T) -> MixMaterial<'a, A, B> {
+        MixMaterial {
+            a,
+            b,
+            weight: weight.into(),
+        }
+    }
+
+    fn weight(&self, hrec: &HitRecord) -> f64 {
+        self.weight.scalar(hrec.u, hrec.v, &hrec.p).clamp(0., 1.)
+    }
+}
+
+impl<'a, A, B> Material for MixMaterial<'a, A, B>
+where
+    A: Material + Copy + Clone,
+    B: Material + Copy + Clone
+{
+    fn scatter(&self, r_in: &Ray, hrec: &HitRecord) -> Option<(Vec3, Ray)> {
+        self.scatter_lobe(r_in, hrec).map(|(attenuation, scattered, _)| (attenuation, scattered))
+    }
+
+    fn scatter_lobe(&self, r_in: &Ray, hrec: &HitRecord) -> Option<(Vec3, Ray, Lobe)> {
+        if rand() < self.weight(hrec) {
+            self.b.scatter_lobe(r_in, hrec)
+        } else {
+            self.a.scatter_lobe(r_in, hrec)
+        }
+    }
+
+    fn eval(&self, r_in: &Ray, hrec: &HitRecord, direction: &Vec3) -> Option<Vec3> {
+        let w = self.weight(hrec);
+        let zero = Vec3::new(0., 0., 0.);
+        match (self.a.eval(r_in, hrec, direction), self.b.eval(r_in, hrec, direction)) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or(zero) * (1. - w) + b.unwrap_or(zero) * w),
+        }
+    }
+
+    fn emitted(&self, r_in: &Ray, hrec: &HitRecord) -> Vec3 {
+        let w = self.weight(hrec);
+        self.a.emitted(r_in, hrec) * (1. - w) + self.b.emitted(r_in, hrec) * w
+    }
+
+    fn emitted_spectral(&self, r_in: &Ray, hrec: &HitRecord, wavelength: f64) -> f64 {
+        let w = self.weight(hrec);
+        self.a.emitted_spectral(r_in, hrec, wavelength) * (1. - w)
+            + self.b.emitted_spectral(r_in, hrec, wavelength) * w
+    }
+
+    fn is_light(&self) -> bool {
+        self.a.is_light() || self.b.is_light()
+    }
+
+    fn is_dispersive(&self) -> bool {
+        self.a.is_dispersive() || self.b.is_dispersive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use material::common::lambertian::Lambertian;
+    use material::common::oren_nayar::OrenNayar;
+    use material::testing::{hit, ray_from, random_above, furnace, assert_reciprocal, assert_close};
+
+    #[test]
+    fn eval_matches_endpoints() {
+        let a = Lambertian::new(Vec3::new(0.2, 0.5, 0.8));
+        let b = OrenNayar::new(Vec3::new(0.9, 0.1, 0.4), 0.5);
+        let hrec = hit(a);
+        for _ in 0..100 {
+            let (view, l) = (random_above(), random_above());
+            let r_in = ray_from(&view);
+            for &(weight, expected) in [(0., &a as &dyn Material), (1., &b)].iter() {
+                let mix = MixMaterial::new(a, b, weight);
+                assert_eq!(mix.eval(&r_in, &hrec, &l).unwrap().e, expected.eval(&r_in, &hrec, &l).unwrap().e);
+            }
+        }
+    }
+
+    #[test]
+    fn blends_albedo() {
+        let white = Lambertian::new(Vec3::new(1., 1., 1.));
+        let black = Lambertian::new(Vec3::new(0., 0., 0.));
+        let hrec = hit(MixMaterial::new(white, black, 0.25));
+        assert_close(&furnace(&hrec, &Vec3::new(0., 0.6, 0.8)), &Vec3::new(0.75, 0.75, 0.75), 0.02);
+        assert_reciprocal(&hit(MixMaterial::new(white, OrenNayar::new(Vec3::new(1., 1., 1.), 0.3), 0.5)));
+    }
+}
@@ -1,3 +1,12 @@
 pub mod lambertian;
 pub mod metal;
 pub mod dielectric;
+pub mod rough_dielectric;
+pub mod emissive;
+pub mod principled;
+pub mod mix;
+pub mod coated;
+pub mod oren_nayar;
+pub mod retroreflective;
+pub mod subsurface;
+pub mod phase;
@@ -0,0 +1,92 @@
+use std::f64::consts::PI;
+
+use vec3::Vec3;
+use ray::Ray;
+use material::Material;
+use hitable::HitRecord;
+use common::sample_cosine_hemisphere;
+
+/// The Oren-Nayar model of rough diffuse surfaces such as plaster or
+/// concrete, where `sigma` is the standard deviation in radians of the
+/// facet slope angle. A `sigma` of zero reduces to `Lambertian`.
+#[derive(Copy, Clone)]
+pub struct OrenNayar {
+    albedo: Vec3,
+    a: f64,
+    b: f64,
+}
+
+impl OrenNayar {
+    pub fn new(albedo: Vec3, sigma: f64) -> OrenNayar {
+        let sigma2 = sigma * sigma;
+        OrenNayar {
+            albedo,
+            a: 1. - 0.5 * sigma2 / (sigma2 + 0.33),
+            b: 0.45 * sigma2 / (sigma2 + 0.09),
+        }
+    }
+
+    /// The BRDF relative to that of a Lambertian surface.
+    fn factor(&self, view: &Vec3, l: &Vec3, n: &Vec3) -> f64 {
+        let cos_v = Vec3::dot(view, n).max(0.);
+        let cos_l = Vec3::dot(l, n).max(0.);
+        let sin_v = (1. - cos_v * cos_v).max(0.).sqrt();
+        let sin_l = (1. - cos_l * cos_l).max(0.).sqrt();
+
+        let mut cos_phi = 0.;
+        if sin_v > 1e-4 && sin_l > 1e-4 {
+            let tangent_v = (*view - *n * cos_v) / sin_v;
+            let tangent_l = (*l - *n * cos_l) / sin_l;
+            cos_phi = Vec3::dot(&tangent_v, &tangent_l).max(0.);
+        }
+        let sin_alpha_tan_beta = sin_v * sin_l / cos_v.max(cos_l).max(1e-4);
+
+        self.a + self.b * cos_phi * sin_alpha_tan_beta
+    }
+}
+
+impl Material for OrenNayar {
+    fn scatter(&self, r_in: &Ray, hrec: &HitRecord) -> Option<(Vec3, Ray)> {
+        let view = -r_in.direction.unit_vector();
+        let l = sample_cosine_hemisphere(&hrec.normal);
+        let weight = self.factor(&view, &l, &hrec.normal);
+        Some((self.albedo * weight, r_in.spawn(hrec.p, l)))
+    }
+
+    fn eval(&self, r_in: &Ray, hrec: &HitRecord, direction: &Vec3) -> Option<Vec3> {
+        let cosine = Vec3::dot(&hrec.normal, direction);
+        if cosine <= 0. {
+            return Some(Vec3::new(0., 0., 0.));
+        }
+        let view = -r_in.direction.unit_vector();
+        Some(self.albedo * (self.factor(&view, direction, &hrec.normal) * cosine / PI))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use material::common::lambertian::Lambertian;
+    use material::testing::{hit, ray_from, random_above, furnace, assert_reciprocal};
+
+    #[test]
+    fn smooth_surface_is_lambertian() {
+        let albedo = Vec3::new(0.2, 0.5, 0.8);
+        let hrec = hit(OrenNayar::new(albedo, 0.));
+        for _ in 0..100 {
+            let (view, l) = (random_above(), random_above());
+            let r_in = ray_from(&view);
+            let lambertian = Lambertian::new(albedo).eval(&r_in, &hrec, &l).unwrap();
+            assert_eq!(hrec.material.eval(&r_in, &hrec, &l).unwrap().e, lambertian.e);
+        }
+    }
+
+    #[test]
+    fn eval_is_reciprocal_and_conserves_energy() {
+        let hrec = hit(OrenNayar::new(Vec3::new(1., 1., 1.), 0.5));
+        assert_reciprocal(&hrec);
+        for &view in [Vec3::new(0., 0., 1.), Vec3::new(0.8, 0., 0.6)].iter() {
+            assert!(furnace(&hrec, &view).e.iter().all(|&a| a > 0. && a <= 1.02));
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use std::f64::consts::PI;
+
+use vec3::Vec3;
+use ray::Ray;
+use material::Material;
+use hitable::HitRecord;
+use common::{random_unit_vector, henyey_greenstein, sample_henyey_greenstein};
+
+/// Scatters light equally in all directions, for participating media.
+#[derive(Copy, Clone)]
+pub struct Isotropic {
+    albedo: Vec3,
+}
+
+impl Isotropic {
+    pub fn new(albedo: Vec3) -> Isotropic {
+        Isotropic {
+            albedo,
+        }
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(&self, r_in: &Ray, hrec: &HitRecord) -> Option<(Vec3, Ray)> {
+        Some((self.albedo, r_in.spawn(hrec.p, random_unit_vector())))
+    }
+
+    fn eval(&self, _r_in: &Ray, _hrec: &HitRecord, _direction: &Vec3) -> Option<Vec3> {
+        Some(self.albedo / (4. * PI))
+    }
+}
+
+/// Scatters light following the Henyey-Greenstein phase function, where
+/// `g` ranges from -1 for back scattering to 1 for forward scattering.
+#[derive(Copy, Clone)]
+pub struct HenyeyGreenstein {
+    albedo: Vec3,
+    g: f64,
+}
+
+impl HenyeyGreenstein {
+    pub fn new(albedo: Vec3, g: f64) -> HenyeyGreenstein {
+        HenyeyGreenstein {
+            albedo,
+            g,
+        }
+    }
+}
+
+impl Material for HenyeyGreenstein {
+    fn scatter(&self, r_in: &Ray, hrec: &HitRecord) -> Option<(Vec3, Ray)> {
+        let direction = sample_henyey_greenstein(&r_in.direction, self.g);
+        Some((self.albedo, r_in.spawn(hrec.p, direction)))
+    }
+
+    fn eval(&self, r_in: &Ray, _hrec: &HitRecord, direction: &Vec3) -> Option<Vec3> {
+        let cosine = Vec3::dot(&r_in.direction.unit_vector(), direction);
+        Some(self.albedo * henyey_greenstein(cosine, self.g))
+    }
+}
@@ -0,0 +1,296 @@
+use std::f64::consts::PI;
+
+use vec3::Vec3;
+use ray::Ray;
+use common::{SPECULAR_ALPHA, rand, sample_ggx, ggx_d, ggx_g1, sample_cosine_hemisphere, to_world, luminance};
+use material::{Material, Lobe};
+use material::common::rough_dielectric::RoughDielectric;
+use hitable::HitRecord;
+use texture::Texture;
+
+/// The Disney principled BSDF (Burley 2012, 2015). Each call to `scatter`
+/// picks one of the diffuse, specular, transmission and clearcoat lobes in
+/// proportion to its weight and samples it.
+#[derive(Copy, Clone)]
+pub struct Principled<'a> {
+    base_color: Texture<'a>,
+    metallic: Texture<'a>,
+    roughness: Texture<'a>,
+    specular: Texture<'a>,
+    specular_tint: Texture<'a>,
+    sheen: Texture<'a>,
+    clearcoat: Texture<'a>,
+    clearcoat_gloss: Texture<'a>,
+    transmission: Texture<'a>,
+    ior: f64,
+}
+
+impl<'a> Principled<'a> {
+    /// A non-metallic material with the given base colour, with a diffuse
+    /// lobe and a moderately rough specular one. The remaining parameters can
+    /// be set on it.
+    pub fn new<T: Into<Texture<'a>>>(base_color: T) -> Principled<'a> {
+        Principled {
+            base_color: base_color.into(),
+            metallic: 0.0.into(),
+            roughness: 0.5.into(),
+            specular: 0.5.into(),
+            specular_tint: 0.0.into(),
+            sheen: 0.0.into(),
+            clearcoat: 0.0.into(),
+            clearcoat_gloss: 1.0.into(),
+            transmission: 0.0.into(),
+            ior: 1.5,
+        }
+    }
+
+    /// Maps the glTF metallic-roughness model, where a metallic-roughness
+    /// image stores roughness in its green and metalness in its blue channel,
+    /// which `Texture::Channel` can select.
+    pub fn from_gltf<T, M, R, S>(base_color: T, metallic: M, roughness: R, transmission: S, ior: f64) -> Principled<'a>
+    where
+        T: Into<Texture<'a>>,
+        M: Into<Texture<'a>>,
+        R: Into<Texture<'a>>,
+        S: Into<Texture<'a>>,
+    {
+        let specular = ((ior - 1.) / (ior + 1.)).powi(2) / 0.08;
+        Principled::new(base_color)
+            .metallic(metallic)
+            .roughness(roughness)
+            .transmission(transmission)
+            .specular(specular)
+            .ior(ior)
+    }
+
+    /// Maps a Wavefront MTL material from its diffuse colour `kd`, specular
+    /// colour `ks`, specular exponent `ns`, optical density `ni` and
+    /// dissolve `d`.
+    pub fn from_mtl<T: Into<Texture<'a>>>(kd: T, ks: Vec3, ns: f64, ni: f64, d: f64) -> Principled<'a> {
+        let roughness = (2. / (ns + 2.)).sqrt().sqrt();
+        Principled::new(kd)
+            .roughness(roughness)
+            .specular(luminance(&ks).min(0.08) / 0.08)
+            .transmission(1. - d)
+            .ior(if ni > 0. { ni } else { 1.5 })
+    }
+
+    pub fn metallic<T: Into<Texture<'a>>>(self, metallic: T) -> Principled<'a> {
+        Principled { metallic: metallic.into(), ..self }
+    }
+
+    pub fn roughness<T: Into<Texture<'a>>>(self, roughness: T) -> Principled<'a> {
+        Principled { roughness: roughness.into(), ..self }
+    }
+
+    pub fn specular<T: Into<Texture<'a>>>(self, specular: T) -> Principled<'a> {
+        Principled { specular: specular.into(), ..self }
+    }
+
+    pub fn specular_tint<T: Into<Texture<'a>>>(self, specular_tint: T) -> Principled<'a> {
+        Principled { specular_tint: specular_tint.into(), ..self }
+    }
+
+    pub fn sheen<T: Into<Texture<'a>>>(self, sheen: T) -> Principled<'a> {
+        Principled { sheen: sheen.into(), ..self }
+    }
+
+    pub fn clearcoat<T: Into<Texture<'a>>>(self, clearcoat: T) -> Principled<'a> {
+        Principled { clearcoat: clearcoat.into(), ..self }
+    }
+
+    pub fn clearcoat_gloss<T: Into<Texture<'a>>>(self, clearcoat_gloss: T) -> Principled<'a> {
+        Principled { clearcoat_gloss: clearcoat_gloss.into(), ..self }
+    }
+
+    pub fn transmission<T: Into<Texture<'a>>>(self, transmission: T) -> Principled<'a> {
+        Principled { transmission: transmission.into(), ..self }
+    }
+
+    pub fn ior(self, ior: f64) -> Principled<'a> {
+        Principled { ior, ..self }
+    }
+}
+
+fn lerp(a: Vec3, b: Vec3, t: f64) -> Vec3 {
+    a * (1. - t) + b * t
+}
+
+fn schlick_weight(cosine: f64) -> f64 {
+    (1. - cosine).clamp(0., 1.).powi(5)
+}
+
+/// Samples a microfacet normal from the GTR1 distribution used by clearcoat.
+fn sample_gtr1(n: &Vec3, alpha: f64) -> Vec3 {
+    let a2 = alpha * alpha;
+    let cos_theta = ((1. - a2.powf(1. - rand())) / (1. - a2)).max(0.).sqrt();
+    let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+    let phi = 2. * PI * rand();
+    to_world(&Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta), n)
+}
+
+/// Reflects `view` about the microfacet normal `m` and returns the sampling
+/// weight of a microfacet lobe with Fresnel reflectance at normal incidence
+/// `f0`, whose distribution was sampled proportionally to `D(m) cos(m, n)`.
+fn microfacet_reflection(view: &Vec3, n: &Vec3, m: &Vec3, f0: Vec3, g_alpha: f64) -> Option<(Vec3, Vec3)> {
+    let cos_vm = Vec3::dot(view, m);
+    let l = *m * (2. * cos_vm) - *view;
+    if cos_vm <= 0. || Vec3::dot(&l, n) <= 0. {
+        return None;
+    }
+    let fresnel = f0 + (Vec3::new(1., 1., 1.) - f0) * schlick_weight(Vec3::dot(&l, m));
+    let g = ggx_g1(view, m, n, g_alpha) * ggx_g1(&l, m, n, g_alpha);
+    let weight = g * cos_vm / (Vec3::dot(view, n) * Vec3::dot(m, n));
+    Some((fresnel * weight, l))
+}
+
+/// The parameters of the lobes at a shading point, with the weight each lobe
+/// is sampled with.
+struct Lobes {
+    base: Vec3,
+    roughness: f64,
+    sheen: f64,
+    f0: Vec3,
+    clearcoat_alpha: f64,
+    diffuse_w: f64,
+    specular_w: f64,
+    transmission_w: f64,
+    clearcoat_w: f64,
+}
+
+impl<'a> Principled<'a> {
+    fn lobes(&self, hrec: &HitRecord) -> Lobes {
+        let (u, v, p) = (hrec.u, hrec.v, &hrec.p);
+        let base = self.base_color.value(u, v, p);
+        let metallic = self.metallic.scalar(u, v, p);
+        let transmission = self.transmission.scalar(u, v, p);
+
+        let lum = luminance(&base);
+        let tint = if lum > 0. { base / lum } else { Vec3::new(1., 1., 1.) };
+        let specular_tint = self.specular_tint.scalar(u, v, p);
+        let dielectric = lerp(Vec3::new(1., 1., 1.), tint, specular_tint)
+                         * (0.08 * self.specular.scalar(u, v, p));
+        let gloss = self.clearcoat_gloss.scalar(u, v, p);
+        let transmission_w = (1. - metallic) * transmission;
+
+        Lobes {
+            base,
+            roughness: self.roughness.scalar(u, v, p),
+            sheen: self.sheen.scalar(u, v, p),
+            f0: lerp(dielectric, base, metallic),
+            clearcoat_alpha: 0.1 * (1. - gloss) + 0.001 * gloss,
+            diffuse_w: (1. - metallic) * (1. - transmission),
+            specular_w: 1. - transmission_w,
+            transmission_w,
+            clearcoat_w: 0.25 * self.clearcoat.scalar(u, v, p),
+        }
+    }
+}
+
+impl Lobes {
+    fn total(&self) -> f64 {
+        self.diffuse_w + self.specular_w + self.transmission_w + self.clearcoat_w
+    }
+
+    fn specular_alpha(&self) -> f64 {
+        (self.roughness * self.roughness).max(1e-3)
+    }
+
+    /// The diffuse and sheen lobe divided by the cosine weighted pdf.
+    fn diffuse(&self, view: &Vec3, l: &Vec3, n: &Vec3) -> Vec3 {
+        let cos_d = Vec3::dot(l, &(*l + *view).unit_vector());
+        let fd90 = 0.5 + 2. * cos_d * cos_d * self.roughness;
+        let fl = schlick_weight(Vec3::dot(n, l));
+        let fv = schlick_weight(Vec3::dot(n, view));
+        let fd = (1. + (fd90 - 1.) * fl) * (1. + (fd90 - 1.) * fv);
+        let sheen = self.sheen * schlick_weight(cos_d) * PI;
+        self.base * fd + Vec3::new(sheen, sheen, sheen)
+    }
+}
+
+/// Evaluates a microfacet reflection lobe with distribution `d` times the
+/// cosine with the normal.
+fn microfacet_eval(view: &Vec3, l: &Vec3, n: &Vec3, d: f64, f0: Vec3, g_alpha: f64) -> Vec3 {
+    let m = (*view + *l).unit_vector();
+    let fresnel = f0 + (Vec3::new(1., 1., 1.) - f0) * schlick_weight(Vec3::dot(l, &m));
+    let g = ggx_g1(view, &m, n, g_alpha) * ggx_g1(l, &m, n, g_alpha);
+    fresnel * (d * g / (4. * Vec3::dot(view, n)))
+}
+
+/// The GTR1 distribution used by clearcoat.
+fn gtr1_d(m: &Vec3, n: &Vec3, alpha: f64) -> f64 {
+    let a2 = alpha * alpha;
+    let cos = Vec3::dot(m, n);
+    (a2 - 1.) / (PI * a2.ln() * (1. + (a2 - 1.) * cos * cos))
+}
+
+impl<'a> Material for Principled<'a> {
+    fn scatter(&self, r_in: &Ray, hrec: &HitRecord) -> Option<(Vec3, Ray)> {
+        self.scatter_lobe(r_in, hrec).map(|(attenuation, scattered, _)| (attenuation, scattered))
+    }
+
+    fn scatter_lobe(&self, r_in: &Ray, hrec: &HitRecord) -> Option<(Vec3, Ray, Lobe)> {
+        let lobes = self.lobes(hrec);
+        let view = -r_in.direction.unit_vector();
+        let inside = Vec3::dot(&view, &hrec.normal).is_sign_negative();
+        if inside && lobes.transmission_w > 0. {
+            return RoughDielectric::new(self.ior, lobes.roughness).scatter_lobe(r_in, hrec);
+        }
+        let n = if inside { -hrec.normal } else { hrec.normal };
+
+        let total = lobes.total();
+        let pick = rand() * total;
+
+        let (attenuation, l, lobe) = if pick < lobes.diffuse_w {
+            let l = sample_cosine_hemisphere(&n);
+            (lobes.diffuse(&view, &l, &n), l, Lobe::Evaluated)
+        } else if pick < lobes.diffuse_w + lobes.specular_w {
+            let alpha = lobes.specular_alpha();
+            let (attenuation, l) = microfacet_reflection(&view, &n, &sample_ggx(&n, alpha), lobes.f0, alpha)?;
+            (attenuation, l, if alpha <= SPECULAR_ALPHA { Lobe::Delta } else { Lobe::Evaluated })
+        } else if pick < lobes.diffuse_w + lobes.specular_w + lobes.transmission_w {
+            let dielectric = RoughDielectric::new(self.ior, lobes.roughness);
+            let (attenuation, scattered, lobe) = dielectric.scatter_lobe(r_in, hrec)?;
+            (attenuation * lobes.base, scattered.direction, lobe)
+        } else {
+            let f0 = Vec3::new(0.04, 0.04, 0.04);
+            let (attenuation, l) = microfacet_reflection(&view, &n, &sample_gtr1(&n, lobes.clearcoat_alpha), f0, 0.25)?;
+            (attenuation, l, Lobe::Evaluated)
+        };
+
+        Some((attenuation * total, r_in.spawn(hrec.p, l), lobe))
+    }
+
+    fn eval(&self, r_in: &Ray, hrec: &HitRecord, direction: &Vec3) -> Option<Vec3> {
+        let lobes = self.lobes(hrec);
+        let view = -r_in.direction.unit_vector();
+        let inside = Vec3::dot(&view, &hrec.normal).is_sign_negative();
+        let dielectric = RoughDielectric::new(self.ior, lobes.roughness);
+        if inside && lobes.transmission_w > 0. {
+            return dielectric.eval(r_in, hrec, direction);
+        }
+        let transmission = match dielectric.eval(r_in, hrec, direction) {
+            Some(value) if lobes.transmission_w > 0. => value * lobes.base * lobes.transmission_w,
+            _ => Vec3::new(0., 0., 0.),
+        };
+        let n = if inside { -hrec.normal } else { hrec.normal };
+        let cos_l = Vec3::dot(direction, &n);
+        if cos_l <= 0. || Vec3::dot(&view, &n) <= 0. {
+            return Some(transmission);
+        }
+
+        let m = (view + *direction).unit_vector();
+        let alpha = lobes.specular_alpha();
+        let diffuse = lobes.diffuse(&view, direction, &n) * (lobes.diffuse_w * cos_l / PI);
+        // A mirror-like specular lobe is sampled by `scatter_lobe` as a delta.
+        let specular = if alpha <= SPECULAR_ALPHA {
+            Vec3::new(0., 0., 0.)
+        } else {
+            microfacet_eval(&view, direction, &n, ggx_d(&m, &n, alpha), lobes.f0, alpha) * lobes.specular_w
+        };
+        let clearcoat = microfacet_eval(&view, direction, &n, gtr1_d(&m, &n, lobes.clearcoat_alpha),
+                                        Vec3::new(0.04, 0.04, 0.04), 0.25)
+                        * lobes.clearcoat_w;
+        Some(diffuse + specular + clearcoat + transmission)
+    }
+}
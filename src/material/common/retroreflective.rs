@@ -0,0 +1,82 @@
+use std::f64::consts::PI;
+
+use vec3::Vec3;
+use ray::Ray;
+use material::Material;
+use hitable::HitRecord;
+use common::sample_cosine_hemisphere;
+
+/// A dusty, retro-reflective surface such as the moon or dry clay, using the
+/// Lommel-Seeliger law with the opposition surge of Hapke. `albedo` is the
+/// single scattering albedo of the particles, while `surge` and `width` set
+/// the strength and angular width of the brightening towards the light.
+#[derive(Copy, Clone)]
+pub struct Retroreflective {
+    albedo: Vec3,
+    surge: f64,
+    width: f64,
+}
+
+impl Retroreflective {
+    pub fn new(albedo: Vec3, surge: f64, width: f64) -> Retroreflective {
+        Retroreflective {
+            albedo,
+            surge,
+            width,
+        }
+    }
+
+    /// The BRDF divided by the albedo, 1 / (4 pi (cos_v + cos_l)) times the
+    /// opposition surge.
+    fn brdf(&self, view: &Vec3, l: &Vec3, n: &Vec3) -> f64 {
+        let cos_v = Vec3::dot(view, n).max(0.);
+        let cos_l = Vec3::dot(l, n).max(0.);
+        if cos_v + cos_l <= 0. {
+            return 0.;
+        }
+        let phase = Vec3::dot(view, l).clamp(-1., 1.).acos();
+        let opposition = 1. + self.surge / (1. + (0.5 * phase).tan() / self.width);
+        opposition / (4. * PI * (cos_v + cos_l))
+    }
+}
+
+impl Material for Retroreflective {
+    fn scatter(&self, r_in: &Ray, hrec: &HitRecord) -> Option<(Vec3, Ray)> {
+        let view = -r_in.direction.unit_vector();
+        let l = sample_cosine_hemisphere(&hrec.normal);
+        // Sampled with a pdf of cos_l / pi.
+        let weight = self.brdf(&view, &l, &hrec.normal) * PI;
+        Some((self.albedo * weight, r_in.spawn(hrec.p, l)))
+    }
+
+    fn eval(&self, r_in: &Ray, hrec: &HitRecord, direction: &Vec3) -> Option<Vec3> {
+        let view = -r_in.direction.unit_vector();
+        let cosine = Vec3::dot(&hrec.normal, direction).max(0.);
+        Some(self.albedo * (self.brdf(&view, direction, &hrec.normal) * cosine))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use material::testing::{hit, ray_from, furnace, assert_reciprocal};
+
+    #[test]
+    fn eval_is_reciprocal_and_conserves_energy() {
+        let hrec = hit(Retroreflective::new(Vec3::new(1., 1., 1.), 1., 0.1));
+        assert_reciprocal(&hrec);
+        for &view in [Vec3::new(0., 0., 1.), Vec3::new(0.8, 0., 0.6)].iter() {
+            assert!(furnace(&hrec, &view).e.iter().all(|&a| a > 0. && a <= 1.02));
+        }
+    }
+
+    #[test]
+    fn brightens_towards_the_light() {
+        let hrec = hit(Retroreflective::new(Vec3::new(0.5, 0.5, 0.5), 1., 0.1));
+        let view = Vec3::new(0.6, 0., 0.8);
+        let r_in = ray_from(&view);
+        let back = hrec.material.eval(&r_in, &hrec, &view).unwrap();
+        let forward = hrec.material.eval(&r_in, &hrec, &Vec3::new(-0.6, 0., 0.8)).unwrap();
+        assert!(back.e[0] > 1.5 * forward.e[0]);
+    }
+}
@@ -0,0 +1,107 @@
+use vec3::Vec3;
+use ray::Ray;
+use common::{SPECULAR_ALPHA, fresnel_dielectric, sample_ggx, ggx_d, ggx_g1, rand};
+use material::Material;
+use hitable::HitRecord;
+
+/// A dielectric with a rough interface, modelled with the GGX microfacet
+/// distribution for both reflection and transmission (Walter et al. 2007).
+#[derive(Copy, Clone)]
+pub struct RoughDielectric {
+    ref_idx: f64,
+    alpha: f64,
+}
+
+impl RoughDielectric {
+    pub fn new(ref_idx: f64, roughness: f64) -> RoughDielectric {
+        let alpha = (roughness * roughness).max(1e-4);
+        RoughDielectric {
+            ref_idx,
+            alpha,
+        }
+    }
+
+    /// The normal on the side of `i`, with the indices of refraction on the
+    /// side of `i` and on the other side.
+    fn orient(&self, i: &Vec3, normal: &Vec3) -> (Vec3, f64, f64) {
+        if Vec3::dot(i, normal).is_sign_positive() {
+            (*normal, 1., self.ref_idx)
+        }
+        else {
+            (-*normal, self.ref_idx, 1.)
+        }
+    }
+}
+
+impl Material for RoughDielectric {
+    fn scatter(&self, r_in: &Ray, hrec: &HitRecord) -> Option<(Vec3, Ray)> {
+        let i = -r_in.direction.unit_vector();
+        let (n, eta_i, eta_o) = self.orient(&i, &hrec.normal);
+
+        let m = sample_ggx(&n, self.alpha);
+        let cos_im = Vec3::dot(&i, &m);
+        if cos_im <= 0. {
+            return None;
+        }
+
+        let fresnel = fresnel_dielectric(cos_im, eta_o / eta_i);
+        let o = if rand() < fresnel {
+            let o = m * (2. * cos_im) - i;
+            if Vec3::dot(&o, &n) <= 0. {
+                return None;
+            }
+            o
+        } else {
+            let eta = eta_i / eta_o;
+            let root = (1. + eta * eta * (cos_im * cos_im - 1.)).sqrt();
+            let o = m * (eta * cos_im - root) - i * eta;
+            if Vec3::dot(&o, &n) >= 0. {
+                return None;
+            }
+            o
+        };
+
+        let g = ggx_g1(&i, &m, &n, self.alpha) * ggx_g1(&o, &m, &n, self.alpha);
+        let weight = cos_im * g / (Vec3::dot(&i, &n) * Vec3::dot(&m, &n));
+        Some((Vec3::new(weight, weight, weight), r_in.spawn(hrec.p, o)))
+    }
+
+    /// The reflection and transmission BSDFs of Walter et al. (2007) times
+    /// the cosine, matching the weights `scatter` returns. Nearly smooth
+    /// interfaces are left to `scatter`.
+    fn eval(&self, r_in: &Ray, hrec: &HitRecord, direction: &Vec3) -> Option<Vec3> {
+        if self.alpha <= SPECULAR_ALPHA {
+            return None;
+        }
+        let i = -r_in.direction.unit_vector();
+        let o = *direction;
+        let (n, eta_i, eta_o) = self.orient(&i, &hrec.normal);
+        let cos_i = Vec3::dot(&i, &n);
+        let cos_o = Vec3::dot(&o, &n);
+        let zero = Vec3::new(0., 0., 0.);
+
+        let reflected = cos_o > 0.;
+        let m = if reflected { i + o } else { -(i * eta_i + o * eta_o) };
+        if m.squared_length() <= 0. {
+            return Some(zero);
+        }
+        let m = m.unit_vector();
+        let m = if Vec3::dot(&m, &n) < 0. { -m } else { m };
+        let cos_im = Vec3::dot(&i, &m);
+        let cos_om = Vec3::dot(&o, &m);
+        if cos_i <= 0. || cos_im <= 0. || (cos_om > 0.) != reflected {
+            return Some(zero);
+        }
+
+        let d = ggx_d(&m, &n, self.alpha);
+        let g = ggx_g1(&i, &m, &n, self.alpha) * ggx_g1(&o, &m, &n, self.alpha);
+        let fresnel = fresnel_dielectric(cos_im, eta_o / eta_i);
+        let value = if reflected {
+            fresnel * d * g / (4. * cos_i)
+        } else {
+            let denominator = eta_i * cos_im + eta_o * cos_om;
+            (1. - fresnel) * d * g * eta_o * eta_o * cos_im * -cos_om / (cos_i * denominator * denominator)
+        };
+        Some(Vec3::new(value, value, value))
+    }
+}
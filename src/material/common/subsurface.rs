@@ -0,0 +1,160 @@
+use std::f64::consts::PI;
+
+use vec3::Vec3;
+use ray::Ray;
+use common::{fresnel_dielectric, refract, reflect, random_in_unit_sphere, rand, beer_lambert, sample_cosine_hemisphere};
+use material::{Material, Lobe};
+use hitable::HitRecord;
+
+/// Translucent materials such as skin, marble, wax or milk, rendered as a
+/// random walk inside a closed surface behind a smooth dielectric interface.
+///
+/// The walk needs no access to the geometry: every time the ray inside hits
+/// the boundary, the length of the segment it travelled tells whether it was
+/// scattered by the medium before reaching the boundary. Distances are
+/// sampled with one of the channels, picked at random, and weighted by the
+/// balance heuristic over all channels.
+///
+/// Light enters through a smooth interface but leaves the medium through a
+/// diffuse transmission lobe, so that light sources are sampled directly
+/// wherever the walk reaches the boundary, through `eval`. The steps of the
+/// walk are `Lobe::Walk` and light sources are only skipped after the walk
+/// left the medium.
+#[derive(Copy, Clone)]
+pub struct Subsurface {
+    albedo: Vec3,
+    extinction: Vec3,
+    ref_idx: f64,
+}
+
+impl Subsurface {
+    /// Creates a medium with the single scattering `albedo` and the
+    /// `mean_free_path` between interactions of each channel.
+    pub fn new(albedo: Vec3, mean_free_path: Vec3, ref_idx: f64) -> Subsurface {
+        Subsurface {
+            albedo,
+            extinction: Vec3::new(mean_free_path.e[0].recip(),
+                                  mean_free_path.e[1].recip(),
+                                  mean_free_path.e[2].recip()),
+            ref_idx,
+        }
+    }
+
+    /// Enters the medium from the outside, either reflecting or refracting
+    /// with the probability given by the Fresnel reflectance.
+    fn enter(&self, r_in: &Ray, hrec: &HitRecord) -> (Vec3, Ray) {
+        let dot = Vec3::dot(&r_in.direction.unit_vector(), &hrec.normal);
+        let weight = Vec3::new(1., 1., 1.);
+        let reflected = reflect(&r_in.direction, &hrec.normal);
+        match refract(&r_in.direction, &hrec.normal, self.ref_idx.recip()) {
+            Some(refracted) if rand() >= fresnel_dielectric(dot, self.ref_idx) => {
+                (weight, r_in.spawn(hrec.p, refracted))
+            }
+            _ => (weight, r_in.spawn(hrec.p, reflected)),
+        }
+    }
+
+    /// Leaves the medium through the diffuse transmission lobe, or goes on
+    /// with the walk after being reflected back inside with the Fresnel
+    /// reflectance of the ray reaching the boundary.
+    fn exit(&self, r_in: &Ray, hrec: &HitRecord, weight: Vec3) -> (Vec3, Ray, Lobe) {
+        let cos_i = Vec3::dot(&r_in.direction.unit_vector(), &hrec.normal);
+        if rand() < fresnel_dielectric(cos_i, self.ref_idx.recip()) {
+            let reflected = reflect(&r_in.direction, &hrec.normal);
+            return (weight, r_in.spawn(hrec.p, reflected), Lobe::Walk);
+        }
+        (weight, r_in.spawn(hrec.p, sample_cosine_hemisphere(&hrec.normal)), Lobe::Evaluated)
+    }
+}
+
+impl Material for Subsurface {
+    fn scatter(&self, r_in: &Ray, hrec: &HitRecord) -> Option<(Vec3, Ray)> {
+        self.scatter_lobe(r_in, hrec).map(|(attenuation, scattered, _)| (attenuation, scattered))
+    }
+
+    /// Both lobes of the smooth interface outside are specular, only leaving
+    /// the medium is covered by `eval`.
+    fn scatter_lobe(&self, r_in: &Ray, hrec: &HitRecord) -> Option<(Vec3, Ray, Lobe)> {
+        let inside = Vec3::dot(&r_in.direction, &hrec.normal).is_sign_positive();
+        if !inside {
+            let (attenuation, scattered) = self.enter(r_in, hrec);
+            return Some((attenuation, scattered, Lobe::Delta));
+        }
+
+        let distance = hrec.t * r_in.direction.length();
+        let channel = ((rand() * 3.) as usize).min(2);
+        let sampled = -(1. - rand()).ln() / self.extinction.e[channel];
+
+        if sampled < distance {
+            let transmittance = beer_lambert(&self.extinction, sampled);
+            let pdf = Vec3::dot(&(self.extinction * transmittance), &Vec3::new(1., 1., 1.)) / 3.;
+            let weight = self.albedo * self.extinction * transmittance / pdf;
+            let p = r_in.origin + r_in.direction.unit_vector() * sampled;
+            let direction = random_in_unit_sphere().unit_vector();
+            return Some((weight, r_in.spawn(p, direction), Lobe::Walk));
+        }
+
+        let transmittance = beer_lambert(&self.extinction, distance);
+        let probability = Vec3::dot(&transmittance, &Vec3::new(1., 1., 1.)) / 3.;
+        Some(self.exit(r_in, hrec, transmittance / probability))
+    }
+
+    /// Light leaving the medium through the diffuse transmission lobe where
+    /// the walk reaches the boundary, times the transmittance of the segment
+    /// that got there, as `scatter_lobe` weights it. The smooth interface
+    /// seen from the outside cannot be evaluated.
+    fn eval(&self, r_in: &Ray, hrec: &HitRecord, direction: &Vec3) -> Option<Vec3> {
+        let cos_i = Vec3::dot(&r_in.direction.unit_vector(), &hrec.normal);
+        if cos_i <= 0. {
+            return None;
+        }
+        let cos_l = Vec3::dot(direction, &hrec.normal);
+        if cos_l <= 0. {
+            return Some(Vec3::new(0., 0., 0.));
+        }
+        let transmittance = beer_lambert(&self.extinction, hrec.t * r_in.direction.length());
+        let transmission = 1. - fresnel_dielectric(cos_i, self.ref_idx.recip());
+        Some(transmittance * (transmission * cos_l / PI))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use material::testing::{hit, ray_from, evaluated, furnace, assert_close};
+
+    #[test]
+    fn only_evaluates_leaving_the_medium() {
+        let hrec = hit(Subsurface::new(Vec3::new(0.9, 0.9, 0.9), Vec3::new(1., 2., 4.), 1.5));
+        let r_in = ray_from(&Vec3::new(0.6, 0., 0.8));
+        assert!(hrec.material.eval(&r_in, &hrec, &Vec3::new(-0.6, 0., 0.8)).is_none());
+        assert!(hrec.material.scatter_lobe(&r_in, &hrec).unwrap().2 == Lobe::Delta);
+    }
+
+    #[test]
+    fn exit_transmits_what_fresnel_lets_through() {
+        let hrec = hit(Subsurface::new(Vec3::new(0.9, 0.9, 0.9), Vec3::new(1., 2., 4.), 1.5));
+        // Arriving at the boundary from inside, one unit away.
+        let view = -Vec3::new(0.6, 0., 0.8);
+        let transmission = 1. - fresnel_dielectric(0.8, 1.5_f64.recip());
+        let expected = beer_lambert(&Vec3::new(1., 0.5, 0.25), 1.) * transmission;
+        assert_close(&furnace(&hrec, &view), &expected, 0.02);
+    }
+
+    #[test]
+    fn eval_matches_exits() {
+        let hrec = hit(Subsurface::new(Vec3::new(0.9, 0.9, 0.9), Vec3::new(1., 2., 4.), 1.5));
+        let view = -Vec3::new(0.6, 0., 0.8);
+        let r_in = ray_from(&view);
+        let g = |l: &Vec3| (1. + l.e[0]).powi(2);
+
+        const N: usize = 200000;
+        let mut exits = Vec3::new(0., 0., 0.);
+        for _ in 0..N {
+            if let Some((attenuation, scattered, Lobe::Evaluated)) = hrec.material.scatter_lobe(&r_in, &hrec) {
+                exits += attenuation * g(&scattered.direction.unit_vector());
+            }
+        }
+        assert_close(&evaluated(&hrec, &view, g), &(exits / N as f64), 0.03);
+    }
+}
@@ -0,0 +1,55 @@
+/// A wavelength dependent index of refraction. Wavelengths are given in
+/// nanometers, while the Cauchy and Sellmeier coefficients follow the usual
+/// convention of wavelengths in micrometers.
+#[derive(Copy, Clone)]
+pub enum Ior {
+    Constant(f64),
+    Cauchy { a: f64, b: f64 },
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl Ior {
+    pub fn bk7() -> Ior {
+        Ior::Sellmeier {
+            b: [1.03961212, 0.231792344, 1.01046945],
+            c: [6.00069867e-3, 2.00179144e-2, 103.560653],
+        }
+    }
+
+    pub fn fused_silica() -> Ior {
+        Ior::Sellmeier {
+            b: [0.6961663, 0.4079426, 0.8974794],
+            c: [4.67914826e-3, 1.35120631e-2, 97.9340025],
+        }
+    }
+
+    pub fn diamond() -> Ior {
+        Ior::Sellmeier {
+            b: [0.3306, 4.3356, 0.],
+            c: [3.0625e-2, 1.1236e-2, 0.],
+        }
+    }
+
+    pub fn at(&self, wavelength: f64) -> f64 {
+        let l = wavelength * 1e-3;
+        let l2 = l * l;
+        match *self {
+            Ior::Constant(n) => n,
+            Ior::Cauchy { a, b } => a + b / l2,
+            Ior::Sellmeier { b, c } => {
+                let n2 = 1. + (0..3).map(|i| b[i] * l2 / (l2 - c[i])).sum::<f64>();
+                n2.sqrt()
+            }
+        }
+    }
+
+    pub fn is_dispersive(&self) -> bool {
+        !matches!(*self, Ior::Constant(_))
+    }
+}
+
+impl From<f64> for Ior {
+    fn from(n: f64) -> Ior {
+        Ior::Constant(n)
+    }
+}
@@ -1,9 +1,148 @@
 use ray::Ray;
 use vec3::Vec3;
 use hitable::HitRecord;
+use spectrum::RgbSpectrum;
 
 pub mod common;
+pub mod ior;
+pub mod thin_film;
+
+/// The kind of lobe a scattered ray was sampled from.
+#[derive(Copy, Clone, PartialEq)]
+pub enum Lobe {
+    /// A lobe covered by `eval`, so light sources along the ray were already
+    /// sampled directly.
+    Evaluated,
+    /// A perfectly specular lobe, or any other lobe `eval` leaves out, whose
+    /// light is only found by following the ray.
+    Delta,
+    /// A step of a random walk inside a medium, continuing the lobe the walk
+    /// was entered through. Walks take many steps, so these do not count
+    /// towards the depth of the path.
+    Walk,
+}
 
 pub trait Material {
     fn scatter(&self, r_in: &Ray, hrec: &HitRecord) -> Option<(Vec3, Ray)>;
+
+    /// Like `scatter`, also telling which kind of lobe the ray was sampled
+    /// from. Defaults to a lobe covered by `eval` whenever it is defined,
+    /// which materials mixing both kinds of lobes must override.
+    fn scatter_lobe(&self, r_in: &Ray, hrec: &HitRecord) -> Option<(Vec3, Ray, Lobe)> {
+        let lobe = if self.eval(r_in, hrec, &hrec.normal).is_some() { Lobe::Evaluated } else { Lobe::Delta };
+        self.scatter(r_in, hrec).map(|(attenuation, scattered)| (attenuation, scattered, lobe))
+    }
+
+    /// The BSDF times the cosine with the normal, for light arriving from
+    /// the unit vector `direction`. Used to sample light sources directly;
+    /// materials that cannot be evaluated, such as perfectly specular ones,
+    /// return `None` and only receive light they scatter towards.
+    fn eval(&self, _r_in: &Ray, _hrec: &HitRecord, _direction: &Vec3) -> Option<Vec3> {
+        None
+    }
+
+    fn emitted(&self, _r_in: &Ray, _hrec: &HitRecord) -> Vec3 {
+        Vec3::new(0., 0., 0.)
+    }
+
+    /// Emitted radiance at `wavelength`, used when rendering spectrally.
+    /// Defaults to upsampling the RGB emission.
+    fn emitted_spectral(&self, r_in: &Ray, hrec: &HitRecord, wavelength: f64) -> f64 {
+        let emitted = self.emitted(r_in, hrec);
+        if emitted.squared_length() == 0. {
+            return 0.;
+        }
+        RgbSpectrum::unbounded(&emitted).eval(wavelength)
+    }
+
+    /// Whether the emission is also sampled directly as one of the scene's
+    /// lights, so paths that sampled the lights at the previous vertex skip
+    /// it.
+    fn is_light(&self) -> bool {
+        false
+    }
+
+    /// Whether scattering depends on the wavelength of the ray, which forces
+    /// spectral paths down to a single wavelength.
+    fn is_dispersive(&self) -> bool {
+        false
+    }
+}
+
+/// Monte Carlo checks shared by the tests of the materials, which look at a
+/// surface at the origin facing up the z axis.
+#[cfg(test)]
+pub mod testing {
+    use std::f64::consts::PI;
+
+    use super::Material;
+    use ray::Ray;
+    use vec3::Vec3;
+    use hitable::HitRecord;
+    use common::random_unit_vector;
+
+    const SAMPLES: usize = 200000;
+
+    pub fn hit<'a, M: Material + 'a>(material: M) -> HitRecord<'a> {
+        HitRecord::new(1., Vec3::new(0., 0., 0.), Vec3::new(0., 0., 1.), Box::new(material))
+    }
+
+    /// A ray arriving at the origin from the unit vector `view`.
+    pub fn ray_from(view: &Vec3) -> Ray {
+        Ray::new(*view, -*view)
+    }
+
+    /// A random unit vector above the surface.
+    pub fn random_above() -> Vec3 {
+        let v = random_unit_vector();
+        if v.e[2] < 0. { -v } else { v }
+    }
+
+    /// Estimates the integral of `g` times the BSDF and cosine by following
+    /// scattered rays.
+    pub fn scattered<G: Fn(&Vec3) -> f64>(hrec: &HitRecord, view: &Vec3, g: G) -> Vec3 {
+        let r_in = ray_from(view);
+        let mut sum = Vec3::new(0., 0., 0.);
+        for _ in 0..SAMPLES {
+            if let Some((attenuation, scattered)) = hrec.material.scatter(&r_in, hrec) {
+                sum += attenuation * g(&scattered.direction.unit_vector());
+            }
+        }
+        sum / SAMPLES as f64
+    }
+
+    /// Estimates the same integral as `scattered` from `eval` at uniformly
+    /// distributed directions.
+    pub fn evaluated<G: Fn(&Vec3) -> f64>(hrec: &HitRecord, view: &Vec3, g: G) -> Vec3 {
+        let r_in = ray_from(view);
+        let mut sum = Vec3::new(0., 0., 0.);
+        for _ in 0..SAMPLES {
+            let l = random_unit_vector();
+            sum += hrec.material.eval(&r_in, hrec, &l).unwrap() * g(&l);
+        }
+        sum * (4. * PI / SAMPLES as f64)
+    }
+
+    /// The fraction of light arriving from `view` that is reflected or
+    /// transmitted, which a white material keeps at or below one.
+    pub fn furnace(hrec: &HitRecord, view: &Vec3) -> Vec3 {
+        evaluated(hrec, view, |_| 1.)
+    }
+
+    /// Asserts the BSDF is unchanged when the view and light directions
+    /// above the surface are swapped.
+    pub fn assert_reciprocal(hrec: &HitRecord) {
+        for _ in 0..1000 {
+            let (v, l) = (random_above(), random_above());
+            let forward = hrec.material.eval(&ray_from(&v), hrec, &l).unwrap() / l.e[2];
+            let backward = hrec.material.eval(&ray_from(&l), hrec, &v).unwrap() / v.e[2];
+            assert!((forward - backward).length() <= 1e-9 * (1. + backward.length()),
+                    "{:?} != {:?}", forward.e, backward.e);
+        }
+    }
+
+    /// Asserts `a` is within `tolerance` of `b`, relative to the length of `b`.
+    pub fn assert_close(a: &Vec3, b: &Vec3, tolerance: f64) {
+        assert!((*a - *b).length() <= tolerance * b.length(), "{:?} != {:?}", a.e, b.e);
+    }
 }
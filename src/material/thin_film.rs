@@ -0,0 +1,153 @@
+use std::f64::consts::PI;
+
+use vec3::Vec3;
+use hitable::HitRecord;
+use spectrum::{wavelength_to_rgb, LAMBDA_MIN, LAMBDA_MAX};
+use texture::Texture;
+
+const RGB_SAMPLES: usize = 40;
+
+/// What lies beneath a thin film.
+#[derive(Copy, Clone)]
+pub enum Substrate {
+    /// A dielectric with the given index of refraction.
+    Dielectric(f64),
+    /// A conductor with the given reflectance, assumed to reflect with the
+    /// phase shift of a perfect conductor.
+    Conductor(f64),
+}
+
+/// A thin dielectric film over a surface, such as a soap bubble, an oil
+/// slick or the oxide layer of anodized metal, whose interference shifts
+/// the colour of reflections with the viewing angle. The thickness is given
+/// in nanometers.
+#[derive(Copy, Clone)]
+pub struct ThinFilm<'a> {
+    ior: f64,
+    thickness: Texture<'a>,
+}
+
+#[derive(Copy, Clone)]
+struct Complex(f64, f64);
+
+impl Complex {
+    fn add(self, other: Complex) -> Complex {
+        Complex(self.0 + other.0, self.1 + other.1)
+    }
+
+    fn mul(self, other: Complex) -> Complex {
+        Complex(self.0 * other.0 - self.1 * other.1, self.0 * other.1 + self.1 * other.0)
+    }
+
+    fn norm_sqr(self) -> f64 {
+        self.0 * self.0 + self.1 * self.1
+    }
+}
+
+/// Reflectance of the film stack for one polarization, from the amplitude
+/// coefficients of its two interfaces and the phase difference between
+/// consecutive reflections (Airy summation).
+fn airy(r12: f64, r23: f64, phase: f64) -> f64 {
+    let shift = Complex(phase.cos(), phase.sin());
+    let numerator = Complex(r12, 0.).add(shift.mul(Complex(r23, 0.)));
+    let denominator = Complex(1., 0.).add(shift.mul(Complex(r12 * r23, 0.)));
+    numerator.norm_sqr() / denominator.norm_sqr()
+}
+
+impl<'a> ThinFilm<'a> {
+    pub fn new<T: Into<Texture<'a>>>(ior: f64, thickness: T) -> ThinFilm<'a> {
+        ThinFilm {
+            ior,
+            thickness: thickness.into(),
+        }
+    }
+
+    pub fn thickness(&self, hrec: &HitRecord) -> f64 {
+        self.thickness.scalar(hrec.u, hrec.v, &hrec.p)
+    }
+
+    /// Reflectance at `wavelength` for light arriving with cosine `cos_i`
+    /// from a medium with index of refraction `n1`.
+    pub fn reflectance(&self, cos_i: f64, n1: f64, substrate: Substrate, thickness: f64, wavelength: f64) -> f64 {
+        let n2 = self.ior;
+        let cos1 = cos_i.abs().min(1.);
+        let sin1_2 = 1. - cos1 * cos1;
+        let cos2_2 = 1. - sin1_2 * (n1 / n2).powi(2);
+        if cos2_2 <= 0. {
+            return 1.;
+        }
+        let cos2 = cos2_2.sqrt();
+
+        let rs12 = (n1 * cos1 - n2 * cos2) / (n1 * cos1 + n2 * cos2);
+        let rp12 = (n2 * cos1 - n1 * cos2) / (n2 * cos1 + n1 * cos2);
+        let (rs23, rp23) = match substrate {
+            Substrate::Dielectric(n3) => {
+                let cos3_2 = 1. - sin1_2 * (n1 / n3).powi(2);
+                if cos3_2 <= 0. {
+                    return 1.;
+                }
+                let cos3 = cos3_2.sqrt();
+                ((n2 * cos2 - n3 * cos3) / (n2 * cos2 + n3 * cos3),
+                 (n3 * cos2 - n2 * cos3) / (n3 * cos2 + n2 * cos3))
+            }
+            Substrate::Conductor(reflectance) => {
+                let r = -reflectance.clamp(0., 1.).sqrt();
+                (r, r)
+            }
+        };
+
+        let phase = 4. * PI * n2 * thickness * cos2 / wavelength;
+        (0.5 * (airy(rs12, rs23, phase) + airy(rp12, rp23, phase))).clamp(0., 1.)
+    }
+
+    /// Reflectance integrated over the visible range into RGB, with a
+    /// separate substrate for each channel.
+    pub fn reflectance_rgb(&self, cos_i: f64, n1: f64, substrates: [Substrate; 3], thickness: f64) -> Vec3 {
+        let step = (LAMBDA_MAX - LAMBDA_MIN) / RGB_SAMPLES as f64;
+        let mut rgb = Vec3::new(0., 0., 0.);
+        for i in 0..RGB_SAMPLES {
+            let lambda = LAMBDA_MIN + (i as f64 + 0.5) * step;
+            let weight = wavelength_to_rgb(lambda);
+            for (c, substrate) in substrates.iter().enumerate() {
+                rgb.e[c] += weight.e[c] * self.reflectance(cos_i, n1, *substrate, thickness, lambda);
+            }
+        }
+        rgb /= RGB_SAMPLES as f64;
+        Vec3::new(rgb.e[0].clamp(0., 1.), rgb.e[1].clamp(0., 1.), rgb.e[2].clamp(0., 1.))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::fresnel_dielectric;
+
+    #[test]
+    fn vanishing_film_leaves_the_substrate() {
+        let film = ThinFilm::new(1.8, 0.);
+        for i in 0..10 {
+            let cos_i = 0.05 + 0.1 * i as f64;
+            for &lambda in [400., 550., 700.].iter() {
+                let r = film.reflectance(cos_i, 1., Substrate::Dielectric(1.5), 0., lambda);
+                assert!((r - fresnel_dielectric(cos_i, 1.5)).abs() < 1e-9);
+                let r = film.reflectance(cos_i, 1., Substrate::Dielectric(1.5), 1e-6, lambda);
+                assert!((r - fresnel_dielectric(cos_i, 1.5)).abs() < 1e-6);
+            }
+        }
+        let air = ThinFilm::new(1., 0.);
+        assert!((air.reflectance(0.7, 1., Substrate::Conductor(0.6), 300., 500.) - 0.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn quarter_wave_film_cancels_reflection() {
+        let ior = 1.5_f64.sqrt();
+        let film = ThinFilm::new(ior, 0.);
+        let thickness = 550. / (4. * ior);
+        assert!(film.reflectance(1., 1., Substrate::Dielectric(1.5), thickness, 550.) < 1e-9);
+        for i in 0..50 {
+            let r = film.reflectance(0.02 * (i + 1) as f64, 1., Substrate::Dielectric(1.5), thickness,
+                                     LAMBDA_MIN + i as f64 * (LAMBDA_MAX - LAMBDA_MIN) / 50.);
+            assert!(r >= 0. && r <= 1.);
+        }
+    }
+}
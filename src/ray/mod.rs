@@ -3,14 +3,44 @@ use vec3::Vec3;
 #[derive(Copy, Clone)]
 pub struct Ray {
     pub origin: Vec3,
-    pub direction: Vec3
+    pub direction: Vec3,
+    /// Wavelength in nanometers, set once the path has been restricted to a
+    /// single wavelength by a dispersive material.
+    pub wavelength: Option<f64>,
+    /// The moment the ray is cast, for motion blur.
+    pub time: f64,
 }
 
 impl Ray {
     pub fn new(origin: Vec3, direction: Vec3) -> Ray {
         Ray  {
             origin,
-            direction
+            direction,
+            wavelength: None,
+            time: 0.,
+        }
+    }
+
+    /// Creates the next ray along the same path, keeping its wavelength.
+    pub fn spawn(&self, origin: Vec3, direction: Vec3) -> Ray {
+        Ray {
+            origin,
+            direction,
+            ..*self
+        }
+    }
+
+    pub fn with_wavelength(self, wavelength: f64) -> Ray {
+        Ray {
+            wavelength: Some(wavelength),
+            ..self
+        }
+    }
+
+    pub fn with_time(self, time: f64) -> Ray {
+        Ray {
+            time,
+            ..self
         }
     }
 
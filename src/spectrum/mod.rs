@@ -0,0 +1,271 @@
+use std::sync::OnceLock;
+use std::f64::consts::PI;
+
+use vec3::Vec3;
+use common::rand;
+
+pub mod rgb;
+pub mod sampled;
+
+pub use self::rgb::RgbSpectrum;
+pub use self::sampled::SampledSpectrum;
+
+pub const LAMBDA_MIN: f64 = 380.;
+pub const LAMBDA_MAX: f64 = 780.;
+
+/// Wavelength, in nanometers, used to evaluate wavelength dependent
+/// quantities on rays that do not carry a wavelength (the sodium D line).
+pub const REFERENCE_WAVELENGTH: f64 = 589.3;
+
+/// Number of wavelengths carried by each path in spectral mode.
+pub const HERO_COUNT: usize = 4;
+
+pub fn sample_wavelength() -> f64 {
+    LAMBDA_MIN + (LAMBDA_MAX - LAMBDA_MIN) * rand()
+}
+
+/// Samples a hero wavelength and derives the remaining wavelengths of the
+/// path by rotating it over the visible range (Wilkie et al. 2014).
+pub fn sample_hero_wavelengths() -> [f64; HERO_COUNT] {
+    let u = rand();
+    let mut lambdas = [0.; HERO_COUNT];
+    for (k, lambda) in lambdas.iter_mut().enumerate() {
+        let offset = (u + k as f64 / HERO_COUNT as f64).fract();
+        *lambda = LAMBDA_MIN + (LAMBDA_MAX - LAMBDA_MIN) * offset;
+    }
+    lambdas
+}
+
+fn lobe(lambda: f64, mu: f64, sigma_low: f64, sigma_high: f64) -> f64 {
+    let sigma = if lambda < mu { sigma_low } else { sigma_high };
+    let t = (lambda - mu) / sigma;
+    (-0.5 * t * t).exp()
+}
+
+/// The CIE 1931 colour matching functions, using the multi-lobe fit of
+/// Wyman, Sloan and Shirley.
+pub fn cie_xyz(lambda: f64) -> Vec3 {
+    let x = 1.056 * lobe(lambda, 599.8, 37.9, 31.0)
+          + 0.362 * lobe(lambda, 442.0, 16.0, 26.7)
+          - 0.065 * lobe(lambda, 501.1, 20.4, 26.2);
+    let y = 0.821 * lobe(lambda, 568.8, 46.9, 40.5)
+          + 0.286 * lobe(lambda, 530.9, 16.3, 31.1);
+    let z = 1.217 * lobe(lambda, 437.0, 11.8, 36.0)
+          + 0.681 * lobe(lambda, 459.0, 26.0, 13.8);
+    Vec3::new(x, y, z)
+}
+
+/// Converts CIE XYZ to linear sRGB.
+pub fn xyz_to_rgb(xyz: &Vec3) -> Vec3 {
+    let e = xyz.e;
+    Vec3::new( 3.2404542 * e[0] - 1.5371385 * e[1] - 0.4985314 * e[2],
+              -0.9692660 * e[0] + 1.8760108 * e[1] + 0.0415560 * e[2],
+               0.0556434 * e[0] - 0.2040259 * e[1] + 1.0572252 * e[2])
+}
+
+/// The linear sRGB response to an equal energy spectrum of unit radiance.
+fn white_response() -> Vec3 {
+    static WHITE: OnceLock<Vec3> = OnceLock::new();
+    *WHITE.get_or_init(|| {
+        let mut white = Vec3::new(0., 0., 0.);
+        let mut lambda = LAMBDA_MIN;
+        while lambda < LAMBDA_MAX {
+            white += xyz_to_rgb(&cie_xyz(lambda + 0.5));
+            lambda += 1.;
+        }
+        white
+    })
+}
+
+/// Converts CIE XYZ, as integrated from a spectrum, to the output RGB space.
+/// The white point is the equal energy spectrum, which maps to `(1, 1, 1)`.
+pub fn xyz_to_output(xyz: &Vec3) -> Vec3 {
+    xyz_to_rgb(xyz) / white_response()
+}
+
+/// The RGB weight of a single wavelength sampled uniformly over the visible
+/// range, normalized so that averaging it over many samples yields white.
+pub fn wavelength_to_rgb(lambda: f64) -> Vec3 {
+    xyz_to_output(&(cie_xyz(lambda) * (LAMBDA_MAX - LAMBDA_MIN)))
+}
+
+/// Monte Carlo estimate of the CIE XYZ of a path carrying `radiance` at the
+/// uniformly sampled `lambdas`.
+pub fn spectral_to_xyz(lambdas: &[f64; HERO_COUNT], radiance: &[f64; HERO_COUNT]) -> Vec3 {
+    let mut xyz = Vec3::new(0., 0., 0.);
+    for k in 0..HERO_COUNT {
+        xyz += cie_xyz(lambdas[k]) * radiance[k];
+    }
+    xyz * (LAMBDA_MAX - LAMBDA_MIN) / HERO_COUNT as f64
+}
+
+/// Spectral radiance of a black body at `temperature` Kelvin, in
+/// W / (sr m^2 m).
+pub fn planck(lambda: f64, temperature: f64) -> f64 {
+    const C: f64 = 2.99792458e8;
+    const H: f64 = 6.62607015e-34;
+    const KB: f64 = 1.380649e-23;
+    let l = lambda * 1e-9;
+    2. * H * C * C / (l.powi(5) * ((H * C / (l * KB * temperature)).exp() - 1.))
+}
+
+/// Wavelength in nanometers at which a black body at `temperature` Kelvin
+/// emits the most.
+pub fn blackbody_peak(temperature: f64) -> f64 {
+    2.8977721e-3 / temperature * 1e9
+}
+
+/// Spectral radiance of a black body at `temperature` Kelvin, normalized so
+/// that its peak equals one.
+pub fn blackbody(lambda: f64, temperature: f64) -> f64 {
+    planck(lambda, temperature) / planck(blackbody_peak(temperature), temperature)
+}
+
+/// Luminous efficacy of radiation at 555nm, in lumens per watt.
+const LUMINOUS_EFFICACY: f64 = 683.;
+
+/// Stefan-Boltzmann constant, in W/(m^2 K^4).
+const STEFAN_BOLTZMANN: f64 = 5.670374419e-8;
+
+/// The integral over the visible range of the CIE `Y` response times
+/// `spectrum`, with wavelengths in nanometers.
+fn luminance_integral<F: Fn(f64) -> f64>(spectrum: F) -> f64 {
+    let mut y = 0.;
+    let mut lambda = LAMBDA_MIN;
+    while lambda < LAMBDA_MAX {
+        y += cie_xyz(lambda + 0.5).e[1] * spectrum(lambda + 0.5);
+        lambda += 1.;
+    }
+    y
+}
+
+/// Luminance in cd/m^2 of a scene radiance of one, which is that of an equal
+/// energy spectrum of 1 W/(m^2 sr nm).
+pub fn unit_luminance() -> f64 {
+    LUMINOUS_EFFICACY * luminance_integral(|_| 1.)
+}
+
+/// The brightness of a black body emitter.
+#[derive(Copy, Clone)]
+pub enum Intensity {
+    /// The colour of the black body, as bright as a white emitter with the
+    /// given radiance.
+    Normalized(f64),
+    /// The spectral radiance of an ideal black body in W/(m^2 sr nm), times
+    /// the given factor.
+    Absolute(f64),
+    /// Radiant power in watts, most of which is infrared at the temperature
+    /// of an incandescent bulb.
+    Watts(f64),
+    /// Luminous power in lumens.
+    Lumens(f64),
+}
+
+impl Intensity {
+    /// The spectrum of a black body at `temperature` Kelvin. Power is spread
+    /// over `extent`, which is the area times `pi` for the radiance of a
+    /// surface and `4 pi` for the intensity of a point light.
+    pub fn blackbody(&self, temperature: f64, extent: f64) -> Spectrum {
+        let peak = planck(blackbody_peak(temperature), temperature);
+        let scale = match *self {
+            Intensity::Normalized(value) => {
+                value * luminance_integral(|_| 1.) / luminance_integral(|l| blackbody(l, temperature))
+            }
+            Intensity::Absolute(factor) => factor * peak * 1e-9,
+            Intensity::Watts(watts) => {
+                let total = STEFAN_BOLTZMANN * temperature.powi(4) / PI / peak * 1e9;
+                watts / extent / total
+            }
+            Intensity::Lumens(lumens) => {
+                let luminous = LUMINOUS_EFFICACY * luminance_integral(|l| blackbody(l, temperature));
+                lumens / extent / luminous
+            }
+        };
+        Spectrum::Blackbody { temperature, scale }
+    }
+}
+
+/// An emission or reflectance spectrum over the visible range. Materials are
+/// `Copy`, so measured samples are stored inline rather than boxed.
+#[allow(clippy::large_enum_variant)]
+#[derive(Copy, Clone)]
+pub enum Spectrum {
+    Constant(f64),
+    Blackbody { temperature: f64, scale: f64 },
+    Rgb(RgbSpectrum),
+    Sampled(SampledSpectrum),
+}
+
+impl Spectrum {
+    pub fn eval(&self, lambda: f64) -> f64 {
+        match *self {
+            Spectrum::Constant(v) => v,
+            Spectrum::Blackbody { temperature, scale } => scale * blackbody(lambda, temperature),
+            Spectrum::Rgb(ref s) => s.eval(lambda),
+            Spectrum::Sampled(ref s) => s.eval(lambda),
+        }
+    }
+
+    /// The output RGB colour of the spectrum, used when rendering in RGB.
+    pub fn to_rgb(&self) -> Vec3 {
+        let mut xyz = Vec3::new(0., 0., 0.);
+        let mut lambda = LAMBDA_MIN;
+        while lambda < LAMBDA_MAX {
+            xyz += cie_xyz(lambda + 0.5) * self.eval(lambda + 0.5);
+            lambda += 1.;
+        }
+        xyz_to_output(&xyz)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The integral of `f` over `[from, to]` nanometers with the midpoint
+    /// rule on a logarithmic scale.
+    fn integrate<F: Fn(f64) -> f64>(f: F, from: f64, to: f64) -> f64 {
+        const STEPS: usize = 100000;
+        let ratio = (to / from).ln() / STEPS as f64;
+        (0..STEPS).map(|i| {
+            let lambda = from * ((i as f64 + 0.5) * ratio).exp();
+            f(lambda) * lambda * ratio
+        }).sum()
+    }
+
+    #[test]
+    fn blackbody_peaks_at_one() {
+        let temperature = 5778.;
+        let peak = blackbody_peak(temperature);
+        assert!((peak - 501.5).abs() < 0.1);
+        assert!((blackbody(peak, temperature) - 1.).abs() < 1e-12);
+        assert!(blackbody(peak - 50., temperature) < 1.);
+        assert!(blackbody(peak + 50., temperature) < 1.);
+    }
+
+    #[test]
+    fn planck_integrates_to_stefan_boltzmann() {
+        let temperature = 3000.;
+        let radiance = integrate(|l| planck(l, temperature) * 1e-9, 10., 1e6);
+        let expected = STEFAN_BOLTZMANN * temperature.powi(4) / PI;
+        assert!((radiance / expected - 1.).abs() < 1e-3);
+    }
+
+    #[test]
+    fn blackbody_has_requested_power() {
+        let temperature = 2700.;
+        let extent = 4. * PI;
+
+        let watts = Intensity::Watts(60.).blackbody(temperature, extent);
+        let power = integrate(|l| watts.eval(l), 10., 1e6) * extent;
+        assert!((power / 60. - 1.).abs() < 1e-3);
+
+        let lumens = Intensity::Lumens(800.).blackbody(temperature, extent);
+        let luminous = LUMINOUS_EFFICACY * luminance_integral(|l| lumens.eval(l)) * extent;
+        assert!((luminous / 800. - 1.).abs() < 1e-9);
+
+        let normalized = Intensity::Normalized(2.).blackbody(temperature, extent);
+        let luminance = luminance_integral(|l| normalized.eval(l));
+        assert!((luminance / luminance_integral(|_| 2.) - 1.).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,179 @@
+use std::sync::OnceLock;
+
+use vec3::Vec3;
+use spectrum::{wavelength_to_rgb, LAMBDA_MIN, LAMBDA_MAX};
+
+const TABLE_RES: usize = 16;
+const FIT_SAMPLES: usize = 80;
+const FIT_STEPS: usize = 8;
+const FIT_ITERATIONS: usize = 6;
+
+/// A smooth spectrum upsampled from an RGB colour, represented as a sigmoid
+/// of a quadratic polynomial in the wavelength (Jakob and Hanika 2019).
+#[derive(Copy, Clone)]
+pub struct RgbSpectrum {
+    coeffs: [f64; 3],
+    scale: f64,
+}
+
+impl RgbSpectrum {
+    /// Upsamples a reflectance. The spectrum stays within `[0, 1]` as long as
+    /// the components of `rgb` do.
+    pub fn reflectance(rgb: &Vec3) -> RgbSpectrum {
+        let max = rgb.e[0].max(rgb.e[1]).max(rgb.e[2]);
+        RgbSpectrum::with_scale(rgb, max.max(1.))
+    }
+
+    /// Upsamples an unbounded colour, such as the radiance of an emitter.
+    pub fn unbounded(rgb: &Vec3) -> RgbSpectrum {
+        let max = rgb.e[0].max(rgb.e[1]).max(rgb.e[2]);
+        RgbSpectrum::with_scale(rgb, 2. * max)
+    }
+
+    fn with_scale(rgb: &Vec3, scale: f64) -> RgbSpectrum {
+        let rgb = Vec3::new(rgb.e[0].max(0.), rgb.e[1].max(0.), rgb.e[2].max(0.));
+        if scale <= 0. {
+            return RgbSpectrum { coeffs: [0.; 3], scale: 0. };
+        }
+        if rgb.e[0] == rgb.e[1] && rgb.e[1] == rgb.e[2] {
+            return RgbSpectrum { coeffs: [0.; 3], scale: 2. * rgb.e[0] };
+        }
+        RgbSpectrum {
+            coeffs: lookup(&(rgb / scale)),
+            scale,
+        }
+    }
+
+    pub fn eval(&self, lambda: f64) -> f64 {
+        self.scale * sigmoid(polynomial(&self.coeffs, normalize(lambda)))
+    }
+}
+
+fn normalize(lambda: f64) -> f64 {
+    (lambda - LAMBDA_MIN) / (LAMBDA_MAX - LAMBDA_MIN)
+}
+
+fn polynomial(c: &[f64; 3], t: f64) -> f64 {
+    (c[0] * t + c[1]) * t + c[2]
+}
+
+fn sigmoid(x: f64) -> f64 {
+    0.5 + 0.5 * x / (1. + x * x).sqrt()
+}
+
+fn sigmoid_derivative(x: f64) -> f64 {
+    0.5 * (1. + x * x).powf(-1.5)
+}
+
+/// Normalized wavelengths and their RGB weights, such that a spectrum's RGB
+/// colour is the weighted sum of its values.
+fn fit_weights() -> Vec<(f64, Vec3)> {
+    let step = (LAMBDA_MAX - LAMBDA_MIN) / FIT_SAMPLES as f64;
+    (0..FIT_SAMPLES).map(|i| {
+        let lambda = LAMBDA_MIN + (i as f64 + 0.5) * step;
+        (normalize(lambda), wavelength_to_rgb(lambda) / FIT_SAMPLES as f64)
+    }).collect()
+}
+
+/// The residual of the RGB colour of `coeffs` against `target`, along with
+/// its Jacobian as one column per coefficient.
+fn residual(coeffs: &[f64; 3], target: &Vec3, weights: &[(f64, Vec3)]) -> (Vec3, [Vec3; 3]) {
+    let mut rgb = Vec3::new(0., 0., 0.);
+    let mut jacobian = [Vec3::new(0., 0., 0.); 3];
+    for &(t, w) in weights {
+        let x = polynomial(coeffs, t);
+        rgb += w * sigmoid(x);
+        let d = sigmoid_derivative(x);
+        jacobian[0] += w * (d * t * t);
+        jacobian[1] += w * (d * t);
+        jacobian[2] += w * d;
+    }
+    (rgb - target, jacobian)
+}
+
+fn determinant(a: &Vec3, b: &Vec3, c: &Vec3) -> f64 {
+    Vec3::dot(a, &Vec3::cross(b, c))
+}
+
+/// Fits the coefficients for `target` with Gauss-Newton, moving the target
+/// gradually away from grey so that each step starts close to a solution.
+fn fit(target: &Vec3, weights: &[(f64, Vec3)]) -> [f64; 3] {
+    let grey = Vec3::new(0.5, 0.5, 0.5);
+    let mut coeffs = [0.; 3];
+    for step in 1..=FIT_STEPS {
+        let s = step as f64 / FIT_STEPS as f64;
+        let goal = grey * (1. - s) + *target * s;
+        for _ in 0..FIT_ITERATIONS {
+            let (res, jac) = residual(&coeffs, &goal, weights);
+            let det = determinant(&jac[0], &jac[1], &jac[2]);
+            if det.abs() < 1e-15 {
+                break;
+            }
+            let delta = [
+                -determinant(&res, &jac[1], &jac[2]) / det,
+                -determinant(&jac[0], &res, &jac[2]) / det,
+                -determinant(&jac[0], &jac[1], &res) / det,
+            ];
+
+            let error = res.squared_length();
+            let mut damping = 1.;
+            for _ in 0..8 {
+                let candidate = [coeffs[0] + damping * delta[0],
+                                 coeffs[1] + damping * delta[1],
+                                 coeffs[2] + damping * delta[2]];
+                if residual(&candidate, &goal, weights).0.squared_length() < error {
+                    coeffs = candidate;
+                    break;
+                }
+                damping *= 0.5;
+            }
+        }
+    }
+    coeffs
+}
+
+fn table() -> &'static Vec<[f64; 3]> {
+    static TABLE: OnceLock<Vec<[f64; 3]>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let weights = fit_weights();
+        let step = (TABLE_RES - 1) as f64;
+        let mut table = Vec::with_capacity(TABLE_RES * TABLE_RES * TABLE_RES);
+        for i in 0..TABLE_RES {
+            for j in 0..TABLE_RES {
+                for k in 0..TABLE_RES {
+                    let target = Vec3::new(i as f64 / step, j as f64 / step, k as f64 / step);
+                    table.push(fit(&target, &weights));
+                }
+            }
+        }
+        table
+    })
+}
+
+/// Trilinearly interpolates the fitted coefficients for a colour in `[0, 1]`.
+fn lookup(rgb: &Vec3) -> [f64; 3] {
+    let table = table();
+    let step = (TABLE_RES - 1) as f64;
+    let mut base = [0; 3];
+    let mut frac = [0.; 3];
+    for c in 0..3 {
+        let x = rgb.e[c].min(1.) * step;
+        base[c] = (x as usize).min(TABLE_RES - 2);
+        frac[c] = x - base[c] as f64;
+    }
+
+    let mut coeffs = [0.; 3];
+    for corner in 0..8 {
+        let mut index = 0;
+        let mut weight = 1.;
+        for c in 0..3 {
+            let bit = (corner >> (2 - c)) & 1;
+            index = index * TABLE_RES + base[c] + bit;
+            weight *= if bit == 1 { frac[c] } else { 1. - frac[c] };
+        }
+        for (coeff, fitted) in coeffs.iter_mut().zip(table[index].iter()) {
+            *coeff += weight * fitted;
+        }
+    }
+    coeffs
+}
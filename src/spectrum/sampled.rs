@@ -0,0 +1,127 @@
+use std::fs::File;
+use std::io::{self, Read};
+
+use spectrum::{LAMBDA_MIN, LAMBDA_MAX};
+
+const SAMPLES: usize = 81;
+const STEP: f64 = (LAMBDA_MAX - LAMBDA_MIN) / (SAMPLES - 1) as f64;
+
+/// A measured spectrum, resampled every 5nm over the visible range.
+#[derive(Copy, Clone)]
+pub struct SampledSpectrum {
+    values: [f64; SAMPLES],
+}
+
+impl SampledSpectrum {
+    /// Builds a spectrum from `(wavelength, value)` pairs sorted by
+    /// wavelength, interpolating linearly between them. A repeated wavelength
+    /// gives a step in the spectrum.
+    pub fn from_samples(samples: &[(f64, f64)]) -> SampledSpectrum {
+        let mut values = [0.; SAMPLES];
+        for (i, value) in values.iter_mut().enumerate() {
+            *value = interpolate(samples, LAMBDA_MIN + i as f64 * STEP);
+        }
+        SampledSpectrum {
+            values,
+        }
+    }
+
+    /// Reads a spectrum from a text file with one whitespace or comma
+    /// separated `wavelength value` pair per line. Lines starting with `#`
+    /// are ignored.
+    pub fn from_file(path: &str) -> io::Result<SampledSpectrum> {
+        let mut text = String::new();
+        File::open(path)?.read_to_string(&mut text)?;
+        SampledSpectrum::parse(&text)
+    }
+
+    /// Decodes the contents of a spectrum file, see `from_file`. The pairs
+    /// may come in any order, but each wavelength only once, and there must
+    /// be at least one.
+    pub fn parse(text: &str) -> io::Result<SampledSpectrum> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut samples = Vec::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<f64> = line.split(|c: char| c == ',' || c.is_whitespace())
+                                       .filter(|f| !f.is_empty())
+                                       .map(|f| f.parse::<f64>())
+                                       .collect::<Result<_, _>>()
+                                       .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            if fields.len() != 2 {
+                return Err(invalid(format!("expected a wavelength and a value: {}", line)));
+            }
+            if !fields[0].is_finite() || !fields[1].is_finite() {
+                return Err(invalid(format!("non-finite sample: {}", line)));
+            }
+            samples.push((fields[0], fields[1]));
+        }
+        if samples.is_empty() {
+            return Err(invalid("no samples in spectrum".to_string()));
+        }
+        samples.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        if let Some(pair) = samples.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(invalid(format!("duplicate wavelength: {}", pair[0].0)));
+        }
+        Ok(SampledSpectrum::from_samples(&samples))
+    }
+
+    pub fn eval(&self, lambda: f64) -> f64 {
+        let x = ((lambda - LAMBDA_MIN) / STEP).max(0.).min((SAMPLES - 1) as f64);
+        let i = (x as usize).min(SAMPLES - 2);
+        let t = x - i as f64;
+        self.values[i] * (1. - t) + self.values[i + 1] * t
+    }
+}
+
+fn interpolate(samples: &[(f64, f64)], lambda: f64) -> f64 {
+    match samples.iter().position(|&(l, _)| l >= lambda) {
+        None => samples.last().map_or(0., |s| s.1),
+        Some(0) => samples[0].1,
+        Some(i) => {
+            let (l0, v0) = samples[i - 1];
+            let (l1, v1) = samples[i];
+            if l1 <= l0 {
+                return v1;
+            }
+            v0 + (v1 - v0) * (lambda - l0) / (l1 - l0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_spectrum() {
+        let text = "# wavelength, value\n500, 0.5\n\n400 0.25\n600,\t1\n";
+        let spectrum = SampledSpectrum::parse(text).unwrap();
+        assert_eq!(spectrum.eval(400.), 0.25);
+        assert_eq!(spectrum.eval(450.), 0.375);
+        assert_eq!(spectrum.eval(600.), 1.);
+        assert_eq!(spectrum.eval(LAMBDA_MIN), 0.25);
+        assert_eq!(spectrum.eval(LAMBDA_MAX), 1.);
+    }
+
+    #[test]
+    fn rejects_malformed_spectrum() {
+        let malformed = ["500", "500 x", "nan 1", "500 inf", "500 1\n600 2\n500 3", "500 1 2", "400, 1\n500, 1, 0.5",
+                         "", "# no samples\n\n"];
+        for text in malformed.iter() {
+            let error = SampledSpectrum::parse(text).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn repeated_wavelength_gives_step() {
+        let spectrum = SampledSpectrum::from_samples(&[(400., 1.), (500., 1.), (500., 3.), (600., 3.)]);
+        assert_eq!(spectrum.eval(500.), 1.);
+        assert_eq!(spectrum.eval(505.), 3.);
+        assert!(spectrum.values.iter().all(|v| v.is_finite()));
+    }
+}
@@ -0,0 +1,319 @@
+use std::fs::File;
+use std::io::{self, Read};
+
+use vec3::Vec3;
+
+/// An image with linear RGB pixels, sampled with wrapping texture coordinates.
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3>,
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, pixels: Vec<Vec3>) -> Image {
+        assert_eq!(pixels.len(), width * height);
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Reads a binary (P6) or plain (P3) PPM file. Values are scaled to
+    /// `[0, 1]` but not linearized, see `srgb_to_linear`.
+    pub fn from_ppm(path: &str) -> io::Result<Image> {
+        let mut bytes = Vec::new();
+        File::open(path)?.read_to_end(&mut bytes)?;
+        Image::parse_ppm(&bytes)
+    }
+
+    /// Decodes the contents of a PPM file, see `from_ppm`.
+    pub fn parse_ppm(bytes: &[u8]) -> io::Result<Image> {
+        let mut pos = 0;
+        let mut header = Vec::with_capacity(4);
+        while header.len() < 4 {
+            while pos < bytes.len() && (bytes[pos] as char).is_whitespace() {
+                pos += 1;
+            }
+            if pos < bytes.len() && bytes[pos] == b'#' {
+                while pos < bytes.len() && bytes[pos] != b'\n' {
+                    pos += 1;
+                }
+                continue;
+            }
+            let start = pos;
+            while pos < bytes.len() && !(bytes[pos] as char).is_whitespace() {
+                pos += 1;
+            }
+            if start == pos {
+                return Err(invalid_data("truncated PPM header"));
+            }
+            header.push(String::from_utf8_lossy(&bytes[start..pos]).into_owned());
+        }
+
+        let parse = |s: &str| s.parse::<usize>().map_err(|_| invalid_data("invalid PPM header"));
+        let width = parse(&header[1])?;
+        let height = parse(&header[2])?;
+        let max = parse(&header[3])? as f64;
+        let count = width.checked_mul(height)
+                         .filter(|&n| n > 0 && n.checked_mul(3).is_some())
+                         .ok_or_else(|| invalid_data("invalid PPM size"))?;
+        if max == 0. {
+            return Err(invalid_data("invalid PPM header"));
+        }
+        if header[0] == "P6" && pos >= bytes.len() {
+            return Err(invalid_data("truncated PPM data"));
+        }
+
+        let values: Vec<f64> = match header[0].as_str() {
+            "P6" if max < 256. => bytes[pos + 1..].iter().map(|&b| b as f64 / max).collect(),
+            "P6" => bytes[pos + 1..].chunks_exact(2)
+                                    .map(|b| (((b[0] as usize) << 8) + b[1] as usize) as f64 / max)
+                                    .collect(),
+            "P3" => String::from_utf8_lossy(&bytes[pos..])
+                        .split_whitespace()
+                        .map(|s| parse(s).map(|v| v as f64 / max))
+                        .collect::<io::Result<_>>()?,
+            _ => return Err(invalid_data("unsupported PPM format")),
+        };
+        if values.len() < count * 3 {
+            return Err(invalid_data("truncated PPM data"));
+        }
+
+        let pixels = values.chunks(3)
+                           .take(count)
+                           .map(|c| Vec3::new(c[0], c[1], c[2]))
+                           .collect();
+        Ok(Image::new(width, height, pixels))
+    }
+
+    /// Reads a Radiance HDR (RGBE) file, either flat or run-length encoded,
+    /// into linear radiance values.
+    pub fn from_hdr(path: &str) -> io::Result<Image> {
+        let mut bytes = Vec::new();
+        File::open(path)?.read_to_end(&mut bytes)?;
+        Image::parse_hdr(&bytes)
+    }
+
+    /// Decodes the contents of a Radiance HDR file, see `from_hdr`.
+    pub fn parse_hdr(bytes: &[u8]) -> io::Result<Image> {
+        if !bytes.starts_with(b"#?") {
+            return Err(invalid_data("missing HDR signature"));
+        }
+
+        let mut pos = 0;
+        let mut line = || {
+            if pos >= bytes.len() {
+                return Err(invalid_data("truncated HDR header"));
+            }
+            let start = pos;
+            while pos < bytes.len() && bytes[pos] != b'\n' {
+                pos += 1;
+            }
+            let line = String::from_utf8_lossy(&bytes[start..pos]).into_owned();
+            pos += 1;
+            Ok(line)
+        };
+        loop {
+            let header = line()?;
+            if header.is_empty() {
+                break;
+            }
+            if header.starts_with("FORMAT=") && header != "FORMAT=32-bit_rle_rgbe" {
+                return Err(invalid_data("unsupported HDR format"));
+            }
+        }
+        let resolution = line()?;
+        let fields: Vec<&str> = resolution.split_whitespace().collect();
+        if fields.len() != 4 || fields[0] != "-Y" || fields[2] != "+X" {
+            return Err(invalid_data("unsupported HDR orientation"));
+        }
+        let parse = |s: &str| s.parse::<usize>().map_err(|_| invalid_data("invalid HDR resolution"));
+        let height = parse(fields[1])?;
+        let width = parse(fields[3])?;
+        if width == 0 || height == 0 {
+            return Err(invalid_data("empty HDR image"));
+        }
+        let size = width.checked_mul(height)
+                        .and_then(|n| n.checked_mul(4))
+                        .ok_or_else(|| invalid_data("invalid HDR resolution"))?;
+
+        let mut rgbe = vec![0_u8; size];
+        for scanline in rgbe.chunks_mut(width * 4) {
+            let encoded = (8..0x8000).contains(&width)
+                          && bytes.len() >= pos + 4
+                          && bytes[pos] == 2 && bytes[pos + 1] == 2
+                          && ((bytes[pos + 2] as usize) << 8 | bytes[pos + 3] as usize) == width;
+            if !encoded {
+                if bytes.len() < pos + width * 4 {
+                    return Err(invalid_data("truncated HDR data"));
+                }
+                scanline.copy_from_slice(&bytes[pos..pos + width * 4]);
+                pos += width * 4;
+                continue;
+            }
+
+            pos += 4;
+            for channel in 0..4 {
+                let mut x = 0;
+                while x < width {
+                    let count = *bytes.get(pos).ok_or_else(|| invalid_data("truncated HDR data"))? as usize;
+                    pos += 1;
+                    let (run, literal) = if count > 128 { (count - 128, false) } else { (count, true) };
+                    if run == 0 || x + run > width || bytes.len() < pos + if literal { run } else { 1 } {
+                        return Err(invalid_data("invalid HDR run"));
+                    }
+                    for i in 0..run {
+                        scanline[(x + i) * 4 + channel] = bytes[if literal { pos + i } else { pos }];
+                    }
+                    pos += if literal { run } else { 1 };
+                    x += run;
+                }
+            }
+        }
+
+        let pixels = rgbe.chunks(4)
+                         .map(|c| {
+                             if c[3] == 0 {
+                                 return Vec3::new(0., 0., 0.);
+                             }
+                             let f = 2_f64.powi(c[3] as i32 - 136);
+                             Vec3::new(c[0] as f64 * f, c[1] as f64 * f, c[2] as f64 * f)
+                         })
+                         .collect();
+        Ok(Image::new(width, height, pixels))
+    }
+
+    /// Decodes the sRGB transfer function, as needed for colour textures.
+    pub fn srgb_to_linear(mut self) -> Image {
+        let decode = |c: f64| {
+            if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+        };
+        for p in self.pixels.iter_mut() {
+            *p = Vec3::new(decode(p.e[0]), decode(p.e[1]), decode(p.e[2]));
+        }
+        self
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Vec3 {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Bilinearly samples the image, with `v` pointing up.
+    pub fn sample(&self, u: f64, v: f64) -> Vec3 {
+        let x = (u - u.floor()) * self.width as f64 - 0.5;
+        let y = (1. - (v - v.floor())) * self.height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let wrap = |i: f64, n: usize| (i as i64).rem_euclid(n as i64) as usize;
+        let (x0, x1) = (wrap(x0, self.width), wrap(x0 + 1., self.width));
+        let (y0, y1) = (wrap(y0, self.height), wrap(y0 + 1., self.height));
+        let top = self.pixel(x0, y0) * (1. - tx) + self.pixel(x1, y0) * tx;
+        let bottom = self.pixel(x0, y1) * (1. - tx) + self.pixel(x1, y1) * tx;
+        top * (1. - ty) + bottom * ty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_ppm() {
+        let plain = Image::parse_ppm(b"P3\n# comment\n2 1\n255\n255 0 0 0 51 255\n").unwrap();
+        let binary = Image::parse_ppm(b"P6 2 1 255\n\xff\x00\x00\x00\x33\xff").unwrap();
+        for image in [plain, binary].iter() {
+            assert_eq!((image.width(), image.height()), (2, 1));
+            assert_eq!(image.pixel(0, 0).e, [1., 0., 0.]);
+            assert_eq!(image.pixel(1, 0).e, [0., 0.2, 1.]);
+        }
+        let wide = Image::parse_ppm(b"P6 1 1 65535\n\xff\xff\x00\x00\x80\x00").unwrap();
+        assert_eq!(wide.pixel(0, 0).e[0], 1.);
+        assert_eq!(wide.pixel(0, 0).e[2], 32768. / 65535.);
+    }
+
+    #[test]
+    fn rejects_malformed_ppm() {
+        let malformed: [&[u8]; 9] = [
+            b"",
+            b"P6 2 1",
+            b"P6 2 1 255",
+            b"P6 2 1 255\n\xff\x00",
+            b"P3 2 1 255\n1 2 3",
+            b"P3 1 1 255\n1 x 3",
+            b"P6 0 1 255\n",
+            b"P5 1 1 255\n\x00",
+            b"P6 1 1 65535\n\x00\x01\x02\x03\x04",
+        ];
+        for bytes in malformed.iter() {
+            let error = Image::parse_ppm(bytes).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+        assert!(Image::parse_ppm(b"P6 99999999999 99999999999 255\n").is_err());
+    }
+
+    fn hdr(resolution: &str, data: &[u8]) -> Vec<u8> {
+        let mut bytes = format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n{}\n", resolution).into_bytes();
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    #[test]
+    fn reads_flat_hdr() {
+        let image = Image::parse_hdr(&hdr("-Y 1 +X 2", &[128, 64, 0, 129, 10, 20, 30, 0])).unwrap();
+        assert_eq!((image.width(), image.height()), (2, 1));
+        assert_eq!(image.pixel(0, 0).e, [1., 0.5, 0.]);
+        assert_eq!(image.pixel(1, 0).e, [0., 0., 0.]);
+    }
+
+    #[test]
+    fn reads_run_length_encoded_hdr() {
+        let mut data = vec![2, 2, 0, 8];
+        data.extend_from_slice(&[8, 128, 128, 128, 128, 64, 64, 64, 64]);
+        data.extend_from_slice(&[128 + 8, 64]);
+        data.extend_from_slice(&[128 + 4, 32, 128 + 4, 0]);
+        data.extend_from_slice(&[128 + 8, 129]);
+        let image = Image::parse_hdr(&hdr("-Y 1 +X 8", &data)).unwrap();
+        assert_eq!(image.pixel(0, 0).e, [1., 0.5, 0.25]);
+        assert_eq!(image.pixel(7, 0).e, [0.5, 0.5, 0.]);
+    }
+
+    #[test]
+    fn rejects_truncated_hdr() {
+        let truncated: [&[u8]; 5] = [
+            b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe",
+            b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n",
+            b"#?RADIANCE\n\n",
+            &hdr("-Y 2 +X 2", &[128, 64, 0, 129]),
+            &hdr("-Y 1 +X 8", &[2, 2, 0, 8, 128 + 8]),
+        ];
+        for bytes in truncated.iter() {
+            let error = Image::parse_hdr(bytes).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_hdr() {
+        assert!(Image::parse_hdr(b"P6\n1 1\n255\n").is_err());
+        assert!(Image::parse_hdr(&hdr("-Y 0 +X 2", &[])).is_err());
+        assert!(Image::parse_hdr(&hdr("-Y 1 +X 0", &[])).is_err());
+        assert!(Image::parse_hdr(&hdr("+Y 1 +X 1", &[0, 0, 0, 0])).is_err());
+        assert!(Image::parse_hdr(&hdr("-Y 1 +X 8", &[2, 2, 0, 8, 0])).is_err());
+        let error = Image::parse_hdr(&hdr("-Y 99999999999 +X 99999999999", &[])).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
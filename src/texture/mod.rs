@@ -0,0 +1,62 @@
+use vec3::Vec3;
+
+pub mod image;
+
+use self::image::Image;
+
+/// A colour channel of an image.
+#[derive(Copy, Clone)]
+pub enum ImageChannel {
+    Red,
+    Green,
+    Blue,
+}
+
+/// A colour or scalar parameter of a material that may vary over a surface.
+#[derive(Copy, Clone)]
+pub enum Texture<'a> {
+    Constant(Vec3),
+    Checker { even: Vec3, odd: Vec3, scale: f64 },
+    Image(&'a Image),
+    /// A single channel of an image, broadcast to all three components.
+    Channel(&'a Image, ImageChannel),
+}
+
+impl<'a> Texture<'a> {
+    pub fn value(&self, u: f64, v: f64, p: &Vec3) -> Vec3 {
+        match *self {
+            Texture::Constant(colour) => colour,
+            Texture::Checker { even, odd, scale } => {
+                let sines = (scale * p.e[0]).sin() * (scale * p.e[1]).sin() * (scale * p.e[2]).sin();
+                if sines.is_sign_negative() { odd } else { even }
+            }
+            Texture::Image(image) => image.sample(u, v),
+            Texture::Channel(image, channel) => {
+                let c = image.sample(u, v).e[channel as usize];
+                Vec3::new(c, c, c)
+            }
+        }
+    }
+
+    pub fn scalar(&self, u: f64, v: f64, p: &Vec3) -> f64 {
+        self.value(u, v, p).e[0]
+    }
+}
+
+impl<'a> From<Vec3> for Texture<'a> {
+    fn from(colour: Vec3) -> Texture<'a> {
+        Texture::Constant(colour)
+    }
+}
+
+impl<'a> From<f64> for Texture<'a> {
+    fn from(value: f64) -> Texture<'a> {
+        Texture::Constant(Vec3::new(value, value, value))
+    }
+}
+
+impl<'a> From<&'a Image> for Texture<'a> {
+    fn from(image: &'a Image) -> Texture<'a> {
+        Texture::Image(image)
+    }
+}